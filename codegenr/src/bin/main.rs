@@ -1,4 +1,11 @@
-use codegenr_lib::{opt::Opt, run_all_codegenr, OptionsMap};
+use codegenr_lib::{
+  bundle_codegenr, check_all_codegenr, dry_run_all_codegenr,
+  loaders::LoadOptions,
+  opt::{config_http_options, Command, Opt},
+  run_all_codegenr,
+  watch::watch_all_codegenr,
+  OptionsMap,
+};
 use structopt::StructOpt;
 
 fn main() -> Result<(), anyhow::Error> {
@@ -7,7 +14,44 @@ fn main() -> Result<(), anyhow::Error> {
   let options = Opt::from_args();
 
   let cmd = options.cmd.unwrap_or_default();
-  let options_map: OptionsMap = cmd.try_into()?;
-  run_all_codegenr(options_map)?;
+  if let Command::Bundle {
+    source,
+    output,
+    remote_cache,
+    file,
+  } = &cmd
+  {
+    let load_options = LoadOptions {
+      remote_cache: remote_cache.as_ref().map(Into::into),
+      offline: options.offline,
+      http: config_http_options(file)?.unwrap_or_default(),
+      ..Default::default()
+    };
+    bundle_codegenr(source, output, &load_options)?;
+    return Ok(());
+  }
+  let mut options_map: OptionsMap = cmd.try_into()?;
+  if options.offline {
+    options_map.values_mut().for_each(|o| o.offline = true);
+  }
+  if options.watch {
+    watch_all_codegenr(options_map)?;
+  } else if options.check {
+    let reports = check_all_codegenr(options_map, options.fail_fast)?;
+    let mut up_to_date = true;
+    for (name, report) in reports.iter().filter(|(_, r)| !r.is_up_to_date()) {
+      up_to_date = false;
+      println!("`{}` section generated files are not up to date:\n{}", name, report);
+    }
+    if !up_to_date {
+      anyhow::bail!("Generated files are not up to date.");
+    }
+  } else if options.dry_run {
+    for (name, plan) in dry_run_all_codegenr(options_map, options.fail_fast)? {
+      println!("`{}` section:\n{}", name, plan);
+    }
+  } else {
+    run_all_codegenr(options_map, options.fail_fast)?;
+  }
   Ok(())
 }
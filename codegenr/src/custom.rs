@@ -1,6 +1,6 @@
 use glob::PatternError;
 use handlebars::Handlebars;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,19 +18,26 @@ pub enum CustomError {
 }
 
 pub fn handlebars_setup(handlebars: &mut Handlebars, custom_helpers_folders: &[String]) -> Result<(), CustomError> {
+  for f in get_script_files(custom_helpers_folders)? {
+    handlebars_add_script(handlebars, f.as_path())?;
+  }
+  Ok(())
+}
+
+/// Lists all the `.rhai` script files found in the custom helpers paths (files or folders)
+pub fn get_script_files(custom_helpers_folders: &[String]) -> Result<Vec<PathBuf>, CustomError> {
+  let mut files = vec![];
   for path in custom_helpers_folders {
     let p = Path::new(&path);
     if p.is_file() {
-      handlebars_add_script(handlebars, p)?;
+      files.push(p.to_path_buf());
     } else if p.is_dir() {
       let pattern = p.join("**/*.rhai");
       let str_pattern = pattern.to_str().ok_or(CustomError::PathBufToStrConvert)?;
-      for f in glob::glob(str_pattern)?.flatten() {
-        handlebars_add_script(handlebars, f.as_path())?;
-      }
+      files.extend(glob::glob(str_pattern)?.flatten());
     }
   }
-  Ok(())
+  Ok(files)
 }
 
 pub fn handlebars_add_script(handlebars: &mut Handlebars, script_file: impl AsRef<Path> + Clone) -> Result<(), CustomError> {
@@ -1,7 +1,8 @@
 use crate::{
-  custom::CustomError, helpers::HelpersError, loaders::LoaderError, processor::ProcessorError, render::RenderError,
-  resolver::ResolverError, SaverError,
+  custom::CustomError, helpers::HelpersError, loaders::LoaderError, merger::MergerError, normalizer::NormalizerError,
+  processor::ProcessorError, render::RenderError, resolver::ResolverError, selection::SelectionError, SaverError,
 };
+use std::fmt::Display;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,16 +11,117 @@ pub enum CodegenrError {
   Loading(#[from] LoaderError),
   #[error("Error while resolving references: `{0}`")]
   Resolving(#[from] ResolverError),
+  #[error("Error while merging the source documents: `{0}`")]
+  Merging(#[from] MergerError),
+  #[error("Error while normalizing the resolved document: `{0}`")]
+  Normalizing(#[from] NormalizerError),
   #[error("Error while saving intermediate files: `{0}`")]
   Saving(#[from] SaverError),
   #[error("Error while using helpers: `{0}`")]
   Helpers(#[from] HelpersError),
   #[error("Error while adding custom helpers: `{0}`")]
   Customizing(#[from] CustomError),
+  #[error("Error while selecting the nodes to render: `{0}`")]
+  Selecting(#[from] SelectionError),
   #[error("Error while rendering: `{0}`")]
   Rendering(#[from] RenderError),
+  #[error("The `{1}` output path rendered for the `{0}` node contains whitespaces, that a `FILE` instruction does not support.")]
+  OutputPath(String, String),
   #[error("Error while post processing output: `{0}`")]
   Processing(#[from] ProcessorError),
   #[error("TemplateRender error: `{0}`.")]
   RenderTemp(#[from] handlebars::RenderError),
 }
+
+impl CodegenrError {
+  /// Name of the error variant, to know at which step the generation failed
+  pub fn kind(&self) -> &'static str {
+    match self {
+      CodegenrError::Loading(_) => "Loading",
+      CodegenrError::Resolving(_) => "Resolving",
+      CodegenrError::Merging(_) => "Merging",
+      CodegenrError::Normalizing(_) => "Normalizing",
+      CodegenrError::Saving(_) => "Saving",
+      CodegenrError::Helpers(_) => "Helpers",
+      CodegenrError::Customizing(_) => "Customizing",
+      CodegenrError::Selecting(_) => "Selecting",
+      CodegenrError::Rendering(_) => "Rendering",
+      CodegenrError::OutputPath(..) => "OutputPath",
+      CodegenrError::Processing(_) => "Processing",
+      CodegenrError::RenderTemp(_) => "RenderTemp",
+    }
+  }
+}
+
+/// The error of one `codegenr.toml` section
+#[derive(Debug)]
+pub struct SectionError {
+  pub section: String,
+  pub error: CodegenrError,
+}
+
+impl SectionError {
+  pub fn new(section: String, error: CodegenrError) -> Self {
+    Self { section, error }
+  }
+
+  /// Messages of all the errors that caused this section error
+  pub fn causes(&self) -> Vec<String> {
+    let mut causes = vec![];
+    let mut source = std::error::Error::source(&self.error);
+    while let Some(cause) = source {
+      causes.push(cause.to_string());
+      source = cause.source();
+    }
+    causes
+  }
+}
+
+impl Display for SectionError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "- `{}` section, {} error: {}", self.section, self.error.kind(), self.error)?;
+    for cause in self.causes() {
+      writeln!(f, "    caused by: {}", cause)?;
+    }
+    Ok(())
+  }
+}
+
+/// All the errors of a multiple sections run
+#[derive(Debug)]
+pub struct SectionsError(pub Vec<SectionError>);
+
+impl Display for SectionsError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "{} section(s) failed:", self.0.len())?;
+    for e in self.0.iter() {
+      write!(f, "{}", e)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for SectionsError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loaders::LoaderError;
+
+  #[test]
+  fn section_error_should_report_kind_and_causes() {
+    let io = std::io::Error::new(std::io::ErrorKind::NotFound, "not found");
+    let error = SectionError::new(
+      "api".into(),
+      CodegenrError::Resolving(ResolverError::Loading(LoaderError::Read("api.yaml".into(), io))),
+    );
+    assert_eq!(error.error.kind(), "Resolving");
+    assert_eq!(
+      error.causes(),
+      vec![
+        "Loading error: `Can't read file `api.yaml`: `not found`.`.",
+        "Can't read file `api.yaml`: `not found`."
+      ]
+    );
+  }
+}
@@ -0,0 +1,112 @@
+use handlebars::{HelperDef, ScopedJson};
+use serde_json::Value;
+
+use super::handlebars_ext::HandlebarsExt;
+use crate::normalizer::asyncapi::{message_payload, operation_action, operation_messages};
+
+pub const ASYNCAPI_ACTION: &str = "asyncapi_action";
+pub const ASYNCAPI_MESSAGES: &str = "asyncapi_messages";
+pub const ASYNCAPI_PAYLOAD: &str = "asyncapi_payload";
+
+/// Returns the action of an asyncapi operation from the application point of view: `send` or `receive`.
+/// The argument is an asyncapi 2 operation key (`publish` is received, `subscribe` is sent) or an asyncapi 3 operation.
+/// ```
+/// # use codegenr_lib::helpers::*;
+/// # use serde_json::json;
+/// let json = json!({
+///   "channels": {
+///     "user/signedup": { "publish": { "operationId": "onUserSignedUp" } },
+///     "user/welcome": { "subscribe": { "operationId": "sendWelcome" } }
+///   },
+///   "operations": { "sendWelcome": { "action": "send" } }
+/// });
+/// assert_eq!(
+///   exec_template(json, "{{#each channels}}{{#each this}}{{operationId}} {{asyncapi_action @key}}\n{{/each}}{{/each}}{{#each operations}}{{@key}} {{asyncapi_action this}}{{/each}}"),
+///   r#"onUserSignedUp receive
+/// sendWelcome send
+/// sendWelcome send"#
+/// );
+/// ```
+pub struct AsyncapiActionHelper;
+
+impl HelperDef for AsyncapiActionHelper {
+  fn call_inner<'reg: 'rc, 'rc>(
+    &self,
+    h: &handlebars::Helper<'reg, 'rc>,
+    _: &'reg handlebars::Handlebars<'reg>,
+    _: &'rc handlebars::Context,
+    _: &mut handlebars::RenderContext<'reg, 'rc>,
+  ) -> Result<handlebars::ScopedJson<'reg, 'rc>, handlebars::RenderError> {
+    h.ensure_arguments_count(1, ASYNCAPI_ACTION)?;
+    let json = h.get_param_as_json_or_fail(0, ASYNCAPI_ACTION)?;
+    let action = match json {
+      Value::String(key) => operation_action(key),
+      operation => operation["action"].as_str().and_then(operation_action),
+    };
+    Ok(ScopedJson::Derived(action.map(Into::into).unwrap_or(Value::Null)))
+  }
+}
+
+/// Returns the messages of an asyncapi operation as an array:
+/// its asyncapi 2 `message` (or the ones of its `oneOf`), or its asyncapi 3 `messages`
+/// ```
+/// # use codegenr_lib::helpers::*;
+/// # use serde_json::json;
+/// let json = json!({
+///   "subscribe": {
+///     "message": { "oneOf": [{ "name": "turnOn" }, { "name": "turnOff" }] }
+///   }
+/// });
+/// assert_eq!(
+///   exec_template(json, "{{#each (asyncapi_messages subscribe)}}{{name}} {{/each}}"),
+///   "turnOn turnOff "
+/// );
+/// ```
+pub struct AsyncapiMessagesHelper;
+
+impl HelperDef for AsyncapiMessagesHelper {
+  fn call_inner<'reg: 'rc, 'rc>(
+    &self,
+    h: &handlebars::Helper<'reg, 'rc>,
+    _: &'reg handlebars::Handlebars<'reg>,
+    _: &'rc handlebars::Context,
+    _: &mut handlebars::RenderContext<'reg, 'rc>,
+  ) -> Result<handlebars::ScopedJson<'reg, 'rc>, handlebars::RenderError> {
+    h.ensure_arguments_count(1, ASYNCAPI_MESSAGES)?;
+    let operation = h.get_param_as_json_or_fail(0, ASYNCAPI_MESSAGES)?;
+    let messages = operation_messages(operation).into_iter().cloned().collect();
+    Ok(ScopedJson::Derived(Value::Array(messages)))
+  }
+}
+
+/// Returns the payload schema of an asyncapi message, unwrapping the asyncapi 3 `{ schemaFormat, schema }` ones
+/// ```
+/// # use codegenr_lib::helpers::*;
+/// # use serde_json::json;
+/// let json = json!({
+///   "payload": {
+///     "schemaFormat": "application/vnd.aai.asyncapi+json;version=3.0.0",
+///     "schema": { "type": "string" }
+///   }
+/// });
+/// assert_eq!(
+///   exec_template(json, "{{#with (asyncapi_payload this)}}{{type}}{{/with}}"),
+///   "string"
+/// );
+/// ```
+pub struct AsyncapiPayloadHelper;
+
+impl HelperDef for AsyncapiPayloadHelper {
+  fn call_inner<'reg: 'rc, 'rc>(
+    &self,
+    h: &handlebars::Helper<'reg, 'rc>,
+    _: &'reg handlebars::Handlebars<'reg>,
+    _: &'rc handlebars::Context,
+    _: &mut handlebars::RenderContext<'reg, 'rc>,
+  ) -> Result<handlebars::ScopedJson<'reg, 'rc>, handlebars::RenderError> {
+    h.ensure_arguments_count(1, ASYNCAPI_PAYLOAD)?;
+    let message = h.get_param_as_json_or_fail(0, ASYNCAPI_PAYLOAD)?;
+    let (payload, _) = message_payload(message);
+    Ok(ScopedJson::Derived(payload.clone()))
+  }
+}
@@ -6,6 +6,7 @@ use thiserror::Error;
 pub mod handlebars_ext;
 pub mod string_ext;
 
+mod asyncapi;
 mod cases;
 mod debug;
 mod distinct;
@@ -17,7 +18,7 @@ mod params;
 mod regex;
 mod strings;
 
-pub use {self::regex::*, cases::*, debug::*, distinct::*, equals::*, getset::*, math::*, openapi3::*, params::*, strings::*};
+pub use {self::regex::*, asyncapi::*, cases::*, debug::*, distinct::*, equals::*, getset::*, math::*, openapi3::*, params::*, strings::*};
 
 #[derive(Error, Debug)]
 pub enum HelpersError {
@@ -57,6 +58,12 @@ pub fn handlebars_stateless_setup(handlebars: &mut Handlebars) {
 
   handlebars.register_helper(IS_OAPI3_PARAM_REQUIRED, Box::new(IsOApi3ParamRequiredHelper));
   handlebars.register_helper(IS_OAPI3_PROP_REQUIRED, Box::new(IsOApi3PropRequiredHelper));
+  handlebars.register_helper(OAPI3_OPERATIONS, Box::new(OApi3OperationsHelper));
+  handlebars.register_helper(OAPI3_OPERATIONS_BY_TAG, Box::new(OApi3OperationsByTagHelper));
+
+  handlebars.register_helper(ASYNCAPI_ACTION, Box::new(AsyncapiActionHelper));
+  handlebars.register_helper(ASYNCAPI_MESSAGES, Box::new(AsyncapiMessagesHelper));
+  handlebars.register_helper(ASYNCAPI_PAYLOAD, Box::new(AsyncapiPayloadHelper));
 }
 
 pub fn handlebars_statefull_setup(handlebars: &mut Handlebars, global_params: HashMap<String, Value>) {
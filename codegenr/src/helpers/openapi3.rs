@@ -2,9 +2,12 @@ use handlebars::{HelperDef, ScopedJson};
 use serde_json::Value;
 
 use super::handlebars_ext::HandlebarsExt;
+use crate::normalizer::operations::{group_operations, openapi3_operations, GroupOperationsBy};
 
 pub const IS_OAPI3_PARAM_REQUIRED: &str = "is_oapi3_parameter_required";
 pub const IS_OAPI3_PROP_REQUIRED: &str = "is_oapi3_property_required";
+pub const OAPI3_OPERATIONS: &str = "oapi3_operations";
+pub const OAPI3_OPERATIONS_BY_TAG: &str = "oapi3_operations_by_tag";
 
 /// Returns true is the current context is an open api 3 required parameter.
 /// ```
@@ -112,3 +115,88 @@ impl HelperDef for IsOApi3PropRequiredHelper {
     Ok(ScopedJson::Derived(Value::Bool(required)))
   }
 }
+
+/// Returns the flat list of the operations of an open api 3 document, like the `operations` normalization does
+/// ```
+/// # use codegenr_lib::helpers::*;
+/// # use serde_json::json;
+/// let json = json!({
+///   "openapi": "3.0.3",
+///   "paths": {
+///     "/pets/{id}": {
+///       "parameters": [{ "name": "id", "in": "path", "required": true }],
+///       "get": { "operationId": "getPet", "responses": { "200": { "description": "The pet" } } },
+///       "delete": { "responses": { "204": { "description": "Deleted" } } }
+///     }
+///   }
+/// });
+/// assert_eq!(
+///   exec_template(json, "{{#each (oapi3_operations this)}}{{operationId}} {{method}} {{path}} {{successResponse.status}} {{#each parameters.path}}{{name}}{{/each}}\n{{/each}}"),
+///   r#"getPet get /pets/{id} 200 id
+/// deletePetsId delete /pets/{id} 204 id
+/// "#
+/// );
+/// ```
+pub struct OApi3OperationsHelper;
+
+impl HelperDef for OApi3OperationsHelper {
+  fn call_inner<'reg: 'rc, 'rc>(
+    &self,
+    h: &handlebars::Helper<'reg, 'rc>,
+    _: &'reg handlebars::Handlebars<'reg>,
+    _: &'rc handlebars::Context,
+    _: &mut handlebars::RenderContext<'reg, 'rc>,
+  ) -> Result<handlebars::ScopedJson<'reg, 'rc>, handlebars::RenderError> {
+    h.ensure_arguments_count(1, OAPI3_OPERATIONS)?;
+    let json = h.get_param_as_json_or_fail(0, OAPI3_OPERATIONS)?;
+    let operations =
+      openapi3_operations(json).map_err(|e| handlebars::RenderError::new(format!("`{}` helper error: {}", OAPI3_OPERATIONS, e)))?;
+    Ok(ScopedJson::Derived(Value::Array(operations)))
+  }
+}
+
+/// Returns the operations of an open api 3 document grouped as `[{ tag, description, operations }]`, like the `tags` normalization does.
+/// They are grouped by their first tag, or by the optional second argument: `path` for the first segment of their path,
+/// or an `x-` extension of the operations.
+/// ```
+/// # use codegenr_lib::helpers::*;
+/// # use serde_json::json;
+/// let json = json!({
+///   "openapi": "3.0.3",
+///   "tags": [{ "name": "pets", "description": "Pets operations" }],
+///   "paths": {
+///     "/pets": { "get": { "operationId": "listPets", "tags": ["pets"] } },
+///     "/stores": { "get": { "operationId": "listStores", "tags": ["pets"], "x-controller": "stores" } }
+///   }
+/// });
+/// assert_eq!(
+///   exec_template(json.clone(), "{{#each (oapi3_operations_by_tag this)}}{{tag}} ({{description}}): {{#each operations}}{{operationId}} {{/each}}\n{{/each}}"),
+///   "pets (Pets operations): listPets listStores \n"
+/// );
+/// assert_eq!(
+///   exec_template(json, "{{#each (oapi3_operations_by_tag this \"x-controller\")}}{{tag}}: {{#each operations}}{{operationId}} {{/each}}\n{{/each}}"),
+///   "pets: listPets \nstores: listStores \n"
+/// );
+/// ```
+pub struct OApi3OperationsByTagHelper;
+
+impl HelperDef for OApi3OperationsByTagHelper {
+  fn call_inner<'reg: 'rc, 'rc>(
+    &self,
+    h: &handlebars::Helper<'reg, 'rc>,
+    _: &'reg handlebars::Handlebars<'reg>,
+    _: &'rc handlebars::Context,
+    _: &mut handlebars::RenderContext<'reg, 'rc>,
+  ) -> Result<handlebars::ScopedJson<'reg, 'rc>, handlebars::RenderError> {
+    h.ensure_arguments_count_min(1, OAPI3_OPERATIONS_BY_TAG)?;
+    h.ensure_arguments_count_max(2, OAPI3_OPERATIONS_BY_TAG)?;
+    let json = h.get_param_as_json_or_fail(0, OAPI3_OPERATIONS_BY_TAG)?;
+    let to_render_error = |e| handlebars::RenderError::new(format!("`{}` helper error: {}", OAPI3_OPERATIONS_BY_TAG, e));
+    let group_by = match h.get_param_as_str(1) {
+      Some(group_by) => group_by.parse::<GroupOperationsBy>().map_err(to_render_error)?,
+      None => Default::default(),
+    };
+    let operations = openapi3_operations(json).map_err(to_render_error)?;
+    Ok(ScopedJson::Derived(Value::Array(group_operations(json, operations, &group_by))))
+  }
+}
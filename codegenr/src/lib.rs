@@ -1,7 +1,8 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
   collections::{BTreeMap, HashMap},
+  io::Write,
   rc::Rc,
 };
 use tracing::{error, info};
@@ -11,11 +12,15 @@ pub mod errors;
 pub(crate) mod filesystem;
 pub mod helpers;
 pub mod loaders;
+pub mod merger;
+pub mod normalizer;
 #[cfg(feature = "bin")]
 pub mod opt;
 pub mod processor;
 pub mod render;
 pub mod resolver;
+pub mod selection;
+pub mod watch;
 
 use filesystem::save_file_content;
 use handlebars::Handlebars;
@@ -24,42 +29,124 @@ use thiserror::Error;
 pub type OptionsMap = BTreeMap<String, Options>;
 
 type OriginalDocumentsHash = HashMap<loaders::DocumentPath, Rc<Value>>;
-type ResolvedDocumentsHash = HashMap<loaders::DocumentPath, Rc<Value>>;
-type HandlebarsHash<'a> = HashMap<HandlebarsReusableConf, (String, Handlebars<'a>)>;
+type ResolvedDocumentsHash = HashMap<(loaders::DocumentPath, resolver::ResolverOptions), Rc<Value>>;
+type HandlebarsHash<'a> = HashMap<HandlebarsReusableConf, (Vec<String>, Handlebars<'a>)>;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 struct HandlebarsReusableConf {
   pub templates: Vec<String>,
   pub custom_helpers: Vec<String>,
+  pub multiple_main_templates: bool,
+  pub override_templates: bool,
+}
+
+impl From<&Options> for HandlebarsReusableConf {
+  fn from(options: &Options) -> Self {
+    Self {
+      templates: options.templates.clone(),
+      custom_helpers: options.custom_helpers.clone(),
+      multiple_main_templates: options.multiple_main_templates,
+      override_templates: options.override_templates,
+    }
+  }
 }
 
 #[derive(Error, Debug)]
 pub enum SaverError {
   #[error("Io Error: `{0}`.")]
   Io(#[from] std::io::Error),
+  #[error("Json serialization error: `{0}`.")]
+  Json(serde_json::Error),
+  #[error("Yaml serialization error: `{0}`.")]
+  Yaml(serde_yaml::Error),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Options {
+  #[serde(default)]
   pub source: String,
+  /// Other source documents, or glob patterns of documents, merged with the `source` one
+  #[serde(default)]
+  pub sources: Vec<String>,
+  /// How the `source` and `sources` documents are merged
+  #[serde(default)]
+  pub merge: merger::MergeStrategy,
   pub output: String,
   pub templates: Vec<String>,
   pub intermediate: Option<String>,
   pub custom_helpers: Vec<String>,
   pub global_parameters: HashMap<String, serde_json::Value>,
+  /// If set, the main template is rendered once for each node matching this selection
+  pub for_each: Option<String>,
+  /// If set with `for_each`, the template of the file each rendering is written to
+  pub output_path: Option<String>,
+  /// If set, every non partial template is a main template, rendered against the same document
+  #[serde(default)]
+  pub multiple_main_templates: bool,
+  /// If set, the templates of a `templates` folder replace the same named ones of the previous folders
+  #[serde(default)]
+  pub override_templates: bool,
+  /// What to do with the circular `$ref`s of the source
+  #[serde(default)]
+  pub circular_refs: resolver::CircularRefs,
+  /// Key under which a `$ref`ed value that is not an object is kept, when the `$ref` has sibling keys
+  pub ref_value_key: Option<String>,
+  /// Folder where the downloaded remote documents are cached
+  pub remote_cache: Option<String>,
+  /// If set, the remote documents are only taken from the `remote_cache`
+  #[serde(default)]
+  pub offline: bool,
+  /// How the remote documents are requested. If not set, the global `[_http]` table of `codegenr.toml` is used
+  pub http: Option<loaders::HttpOptions>,
+  /// How a yaml document holding several `---` separated documents is loaded
+  #[serde(default)]
+  pub yaml_documents: loaders::YamlDocuments,
+  /// Transformations of the resolved document done before rendering it, in their order
+  #[serde(default)]
+  pub normalize: Vec<normalizer::Normalization>,
+}
+
+impl From<&Options> for loaders::LoadOptions {
+  fn from(options: &Options) -> Self {
+    Self {
+      remote_cache: options.remote_cache.as_ref().map(Into::into),
+      offline: options.offline,
+      http: options.http.clone().unwrap_or_default(),
+      yaml_documents: options.yaml_documents.clone(),
+    }
+  }
+}
+
+impl From<&Options> for resolver::ResolverOptions {
+  fn from(options: &Options) -> Self {
+    Self {
+      circular_refs: options.circular_refs,
+      ref_value_key: options.ref_value_key.clone(),
+      load: options.into(),
+    }
+  }
+}
+
+/// The data a `for_each` rendering is executed with
+#[derive(Serialize)]
+struct ForEachContext<'a> {
+  template: &'a str,
+  key: &'a str,
+  node: &'a Value,
+  root: &'a Value,
 }
 
+/// Runs all the sections and reports all the sections errors.
+/// If `fail_fast` is set, the remaining sections are not executed after the first error.
 #[::tracing::instrument(level = "trace")]
-pub fn run_all_codegenr(options_map: OptionsMap) -> Result<(), errors::CodegenrError> {
+pub fn run_all_codegenr(options_map: OptionsMap, fail_fast: bool) -> Result<(), errors::SectionsError> {
   let mut original_cache = Default::default();
   let mut resolved_cache = Default::default();
   let mut reusables = Default::default();
-  for (name, options) in options_map {
+  run_sections(options_map, fail_fast, |name, options| {
     info!("Running code generation section `{}`", name);
-    if let Err(e) = run_codegenr(options, &mut original_cache, &mut resolved_cache, &mut reusables) {
-      error!("Error while executing the `{}` section: `{}`.", name, e);
-    }
-  }
+    run_codegenr(options, &mut original_cache, &mut resolved_cache, &mut reusables)
+  })?;
   Ok(())
 }
 
@@ -71,6 +158,104 @@ pub fn run_one_codegenr(options: Options) -> Result<(), errors::CodegenrError> {
   run_codegenr(options, &mut original_cache, &mut resolved_cache, &mut reusables)
 }
 
+/// Runs one section, writing its files through the given backend instead of the `output` folder
+#[::tracing::instrument(level = "trace")]
+pub fn run_one_codegenr_with_backend(options: Options, backend: Rc<dyn processor::OutputBackend>) -> Result<(), errors::CodegenrError> {
+  let mut original_cache = Default::default();
+  let mut resolved_cache = Default::default();
+  let mut reusables = Default::default();
+  run_codegenr_with_backend(options, backend, &mut original_cache, &mut resolved_cache, &mut reusables)
+}
+
+/// Renders all the sections without writing anything, and returns, for each section,
+/// the actions the `FILE` and `CLEAN` instructions would have executed
+#[::tracing::instrument(level = "trace")]
+pub fn dry_run_all_codegenr(options_map: OptionsMap, fail_fast: bool) -> Result<BTreeMap<String, processor::Plan>, errors::SectionsError> {
+  let mut original_cache = Default::default();
+  let mut resolved_cache = Default::default();
+  let mut reusables = Default::default();
+  run_sections(options_map, fail_fast, |name, options| {
+    info!("Planning code generation section `{}`", name);
+    // Nothing is written while planning, not even the intermediate files
+    let options = Options {
+      intermediate: None,
+      ..options
+    };
+    let backend = Rc::new(processor::DiskBackend::new(options.output.clone()));
+    let rendered = render_codegenr(options, &mut original_cache, &mut resolved_cache, &mut reusables)?;
+    Ok(processor::plan(&rendered, backend)?)
+  })
+}
+
+/// Renders all the sections without writing anything, and reports, for each section,
+/// the differences between what would be generated and what exists in its output folder
+#[::tracing::instrument(level = "trace")]
+pub fn check_all_codegenr(
+  options_map: OptionsMap,
+  fail_fast: bool,
+) -> Result<BTreeMap<String, processor::CheckReport>, errors::SectionsError> {
+  let plans = dry_run_all_codegenr(options_map, fail_fast)?;
+  Ok(plans.into_iter().map(|(name, plan)| (name, plan.into())).collect())
+}
+
+/// Renders one section and returns all the produced output instead of writing it:
+/// the `FILE` contents by relative path, the `CONSOLE` lines and the `CLEAN` patterns.
+/// Only the intermediate files are written, if asked.
+#[::tracing::instrument(level = "trace")]
+pub fn generate(options: Options) -> Result<processor::GeneratedOutput, errors::CodegenrError> {
+  let mut original_cache = Default::default();
+  let mut resolved_cache = Default::default();
+  let mut reusables = Default::default();
+  let rendered = render_codegenr(options, &mut original_cache, &mut resolved_cache, &mut reusables)?;
+  Ok(processor::generate(&rendered)?)
+}
+
+/// Writes the source document in one self-contained output file, its external `$ref`s being hoisted in it.
+/// The output is written as yaml if it has a `.yaml` or `.yml` extension, as json otherwise.
+#[::tracing::instrument(level = "trace")]
+pub fn bundle_codegenr(source: &str, output: &str, load_options: &loaders::LoadOptions) -> Result<(), errors::CodegenrError> {
+  let document = loaders::DocumentPath::parse(source)?;
+  let bundled = resolver::bundle_refs(&document, load_options, &mut Default::default())?;
+  let content = if output.ends_with(".yaml") || output.ends_with(".yml") {
+    serde_yaml::to_string(&bundled).map_err(SaverError::Yaml)?
+  } else {
+    serde_json::to_string_pretty(&bundled).map_err(SaverError::Json)?
+  };
+  let mut file = filesystem::create_file_from_path(&output.into()).map_err(SaverError::Io)?;
+  file.write_all(content.as_bytes()).map_err(SaverError::Io)?;
+  info!("`{}` bundled in `{}`.", source, output);
+  Ok(())
+}
+
+/// Executes `run` on each section, collecting all the sections results and errors
+fn run_sections<T>(
+  options_map: OptionsMap,
+  fail_fast: bool,
+  mut run: impl FnMut(&str, Options) -> Result<T, errors::CodegenrError>,
+) -> Result<BTreeMap<String, T>, errors::SectionsError> {
+  let mut results = BTreeMap::new();
+  let mut errors = vec![];
+  for (name, options) in options_map {
+    match run(&name, options) {
+      Ok(result) => {
+        results.insert(name, result);
+      }
+      Err(e) => {
+        error!("Error while executing the `{}` section: `{}`.", name, e);
+        errors.push(errors::SectionError::new(name, e));
+        if fail_fast {
+          break;
+        }
+      }
+    }
+  }
+  if errors.is_empty() {
+    Ok(results)
+  } else {
+    Err(errors::SectionsError(errors))
+  }
+}
+
 #[::tracing::instrument(level = "trace")]
 fn run_codegenr(
   options: Options,
@@ -78,44 +263,162 @@ fn run_codegenr(
   resolved_cache: &mut ResolvedDocumentsHash,
   reusables: &mut HandlebarsHash,
 ) -> Result<(), errors::CodegenrError> {
-  let document = loaders::DocumentPath::parse(&options.source)?;
-  let json = resolver::resolve_refs(document, original_cache, resolved_cache)?;
+  let backend = Rc::new(processor::DiskBackend::new(options.output.clone()));
+  run_codegenr_with_backend(options, backend, original_cache, resolved_cache, reusables)
+}
+
+#[::tracing::instrument(level = "trace")]
+fn run_codegenr_with_backend(
+  options: Options,
+  backend: Rc<dyn processor::OutputBackend>,
+  original_cache: &mut OriginalDocumentsHash,
+  resolved_cache: &mut ResolvedDocumentsHash,
+  reusables: &mut HandlebarsHash,
+) -> Result<(), errors::CodegenrError> {
+  let rendered = render_codegenr(options, original_cache, resolved_cache, reusables)?;
+  processor::process(&rendered, backend)?;
+  Ok(())
+}
+
+#[::tracing::instrument(level = "trace")]
+fn render_codegenr(
+  options: Options,
+  original_cache: &mut OriginalDocumentsHash,
+  resolved_cache: &mut ResolvedDocumentsHash,
+  reusables: &mut HandlebarsHash,
+) -> Result<String, errors::CodegenrError> {
+  let json = resolve_sources(&options, original_cache, resolved_cache)?;
+  let json = normalizer::normalize(json, &options.normalize)?;
 
   if options.intermediate.is_some() {
     save_intermediate(&options.intermediate, "resolved.json", &format!("{:#}", json))?;
   }
 
-  let (main_template_name, mut handlebars) = reusables
-    .entry(HandlebarsReusableConf {
-      custom_helpers: options.custom_helpers,
-      templates: options.templates,
-    })
-    .or_insert_with_key(|conf| {
-      let mut all_templates = vec![];
-      for t in conf.templates.iter() {
-        let templates = render::get_templates_from_directory(t).unwrap(); //?;
-        all_templates.extend(templates);
-      }
-      let templates = render::TemplateCollection::from_list(all_templates).unwrap(); //?;
-
-      let mut handlebars = Handlebars::new();
-      helpers::handlebars_stateless_setup(&mut handlebars);
-
-      templates.setup_handlebars(&mut handlebars).unwrap(); // todo?;
-      custom::handlebars_setup(&mut handlebars, &conf.custom_helpers).unwrap(); //?;
-      (templates.main_template_name().to_owned(), handlebars)
-    })
-    .clone();
+  use std::collections::hash_map::Entry::*;
+  let (main_template_names, mut handlebars) = match reusables.entry(HandlebarsReusableConf::from(&options)) {
+    Occupied(entry) => entry.get().clone(),
+    Vacant(entry) => {
+      let reusable = setup_handlebars(entry.key())?;
+      entry.insert(reusable).clone()
+    }
+  };
 
   helpers::handlebars_statefull_setup(&mut handlebars, options.global_parameters);
   helpers::handlebars_misc_setup(&mut handlebars);
 
-  let rendered = handlebars.render(&main_template_name, &(*json))?;
+  let mut rendered = String::new();
+  for main_template_name in main_template_names.iter() {
+    if !rendered.is_empty() && !rendered.ends_with('\n') {
+      rendered.push('\n');
+    }
+    match options.for_each.as_deref() {
+      Some(selection) => rendered.push_str(&render_for_each(
+        &handlebars,
+        main_template_name,
+        &json,
+        selection,
+        options.output_path.as_deref(),
+      )?),
+      None => rendered.push_str(&handlebars.render(main_template_name, &(*json))?),
+    }
+  }
 
   save_intermediate(&options.intermediate, "rendered.txt", &rendered)?;
+  Ok(rendered)
+}
 
-  processor::process(&rendered, options.output)?;
-  Ok(())
+/// Renders the main template once for each selected node, and wraps each rendering
+/// in a `FILE` instruction when an `output_path` template is given.
+/// The rendered paths can't contain whitespaces, as the `FILE` instruction would only keep their first word.
+fn render_for_each(
+  handlebars: &Handlebars,
+  main_template_name: &str,
+  document: &Value,
+  selection: &str,
+  output_path: Option<&str>,
+) -> Result<String, errors::CodegenrError> {
+  let nodes = selection::select_nodes(document, selection)?;
+  if nodes.is_empty() {
+    tracing::warn!("No node matches the `{}` selection, nothing is rendered.", selection);
+  }
+
+  let mut rendered = String::new();
+  for selected in nodes {
+    let context = ForEachContext {
+      template: main_template_name,
+      key: &selected.key,
+      node: selected.node,
+      root: document,
+    };
+    let content = handlebars.render(main_template_name, &context)?;
+    match output_path {
+      Some(output_path) => {
+        let path = handlebars.render_template(output_path, &context)?;
+        let path = path.trim();
+        if path.contains(char::is_whitespace) {
+          return Err(errors::CodegenrError::OutputPath(selected.key, path.into()));
+        }
+        rendered.push_str(&format!("### FILE {}\n", path));
+        rendered.push_str(&content);
+        if !content.is_empty() && !content.ends_with('\n') {
+          rendered.push('\n');
+        }
+        rendered.push_str("### /FILE\n");
+      }
+      None => rendered.push_str(&content),
+    }
+  }
+  Ok(rendered)
+}
+
+fn setup_handlebars<'a>(conf: &HandlebarsReusableConf) -> Result<(Vec<String>, Handlebars<'a>), errors::CodegenrError> {
+  let mut folders = vec![];
+  for t in conf.templates.iter() {
+    folders.push(render::get_templates_from_directory(t)?);
+  }
+  let all_templates = if conf.override_templates {
+    render::override_templates(folders, conf.multiple_main_templates)
+  } else {
+    folders.into_iter().flatten().collect()
+  };
+  let templates = if conf.multiple_main_templates {
+    render::TemplateCollection::from_list_with_multiple_mains(all_templates)?
+  } else {
+    render::TemplateCollection::from_list(all_templates)?
+  };
+
+  let mut handlebars = Handlebars::new();
+  helpers::handlebars_stateless_setup(&mut handlebars);
+
+  templates.setup_handlebars(&mut handlebars)?;
+  custom::handlebars_setup(&mut handlebars, &conf.custom_helpers)?;
+  let main_template_names = templates.main_template_names().into_iter().map(Into::into).collect();
+  Ok((main_template_names, handlebars))
+}
+
+/// Resolves the source documents, and merges them if there are several ones
+fn resolve_sources(
+  options: &Options,
+  original_cache: &mut OriginalDocumentsHash,
+  resolved_cache: &mut ResolvedDocumentsHash,
+) -> Result<Rc<Value>, errors::CodegenrError> {
+  let resolver_options = options.into();
+  let mut documents = merger::source_documents(&options.source, &options.sources)?;
+  if documents.len() == 1 {
+    return Ok(resolver::resolve_refs(
+      documents.remove(0),
+      &resolver_options,
+      original_cache,
+      resolved_cache,
+    )?);
+  }
+
+  let mut resolved = Vec::with_capacity(documents.len());
+  for document in documents {
+    let json = resolver::resolve_refs(document.clone(), &resolver_options, original_cache, resolved_cache)?;
+    resolved.push((document, (*json).clone()));
+  }
+  Ok(Rc::new(merger::merge_documents(resolved, options.merge)?))
 }
 
 fn save_intermediate(file: &Option<String>, extension: &str, content: &str) -> Result<(), SaverError> {
@@ -125,3 +428,322 @@ fn save_intermediate(file: &Option<String>, extension: &str, content: &str) -> R
   }
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempdir::TempDir;
+
+  #[test]
+  fn generate_should_not_write_anything() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("GENERATE_tests")?;
+    save_file_content(tmp.path(), "source.yaml", "names: [a, b]")?;
+    save_file_content(
+      tmp.path(),
+      "main.hbs",
+      "### CLEAN *.txt\n{{#each names}}\n### FILE {{this}}.txt\n{{this}}\n### /FILE\n{{/each}}\n### CONSOLE\ndone\n### /CONSOLE",
+    )?;
+    let root = tmp.path().to_string_lossy();
+
+    let generated = generate(Options {
+      source: format!("{root}/source.yaml"),
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      ..Default::default()
+    })?;
+
+    assert_eq!(generated.files.get("a.txt").map(String::as_str), Some("a\n"));
+    assert_eq!(generated.files.get("b.txt").map(String::as_str), Some("b\n"));
+    assert_eq!(generated.console, vec!["done".to_string()]);
+    assert_eq!(generated.clean_patterns, vec!["*.txt".to_string()]);
+    assert!(!tmp.path().join("output").exists());
+    Ok(())
+  }
+
+  #[test]
+  fn dry_run_should_not_write_anything() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("DRY_RUN_tests")?;
+    save_file_content(tmp.path(), "source.yaml", "name: a")?;
+    save_file_content(tmp.path(), "main.hbs", "### FILE {{name}}.txt\n{{name}}\n### /FILE")?;
+    let root = tmp.path().to_string_lossy();
+    let options = Options {
+      source: format!("{root}/source.yaml"),
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      intermediate: Some(format!("{root}/debug")),
+      ..Default::default()
+    };
+
+    let plans = dry_run_all_codegenr(OptionsMap::from_iter([("section".into(), options)]), false)?;
+
+    assert_eq!(plans["section"].actions.len(), 1);
+    assert!(!tmp.path().join("output").exists());
+    assert!(!tmp.path().join("debug.resolved.json").exists());
+    assert!(!tmp.path().join("debug.rendered.txt").exists());
+    Ok(())
+  }
+
+  #[test]
+  fn check_should_not_write_anything() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("CHECK_tests")?;
+    save_file_content(tmp.path(), "source.yaml", "name: a")?;
+    save_file_content(tmp.path(), "main.hbs", "### FILE {{name}}.txt\n{{name}}\n### /FILE")?;
+    std::fs::create_dir(tmp.path().join("output"))?;
+    save_file_content(tmp.path().join("output"), "a.txt", "old\n")?;
+    let root = tmp.path().to_string_lossy();
+    let options = Options {
+      source: format!("{root}/source.yaml"),
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      intermediate: Some(format!("{root}/debug")),
+      ..Default::default()
+    };
+
+    let reports = check_all_codegenr(OptionsMap::from_iter([("section".into(), options)]), false)?;
+
+    assert_eq!(reports["section"].changed, vec![tmp.path().join("output").join("a.txt")]);
+    assert_eq!(std::fs::read_to_string(tmp.path().join("output").join("a.txt"))?, "old\n");
+    assert!(!tmp.path().join("debug.resolved.json").exists());
+    assert!(!tmp.path().join("debug.rendered.txt").exists());
+    Ok(())
+  }
+
+  #[test]
+  fn for_each_should_render_one_file_per_selected_node() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("FOR_EACH_tests")?;
+    save_file_content(
+      tmp.path(),
+      "source.yaml",
+      "title: api\ncomponents:\n  schemas:\n    PetStore: { type: object }\n    Error: { type: string }",
+    )?;
+    save_file_content(tmp.path(), "main.hbs", "{{root.title}} {{key}}: {{node.type}}")?;
+    let root = tmp.path().to_string_lossy();
+
+    let generated = generate(Options {
+      source: format!("{root}/source.yaml"),
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      for_each: Some("/components/schemas/*".into()),
+      output_path: Some("models/{{to_snake_case key}}.rs".into()),
+      ..Default::default()
+    })?;
+
+    assert_eq!(
+      generated.files,
+      [
+        ("models/error.rs".to_string(), "api Error: string\n".to_string()),
+        ("models/pet_store.rs".to_string(), "api PetStore: object\n".to_string())
+      ]
+      .into_iter()
+      .collect()
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn for_each_output_paths_with_whitespaces_should_fail() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("FOR_EACH_tests")?;
+    save_file_content(tmp.path(), "source.yaml", "schemas:\n  Pet Store: { type: object }")?;
+    save_file_content(tmp.path(), "main.hbs", "{{key}}")?;
+    let root = tmp.path().to_string_lossy();
+
+    let result = generate(Options {
+      source: format!("{root}/source.yaml"),
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      for_each: Some("/schemas/*".into()),
+      output_path: Some("models/{{key}}.rs".into()),
+      ..Default::default()
+    });
+
+    assert!(matches!(&result, Err(errors::CodegenrError::OutputPath(key, path)) if key == "Pet Store" && path == "models/Pet Store.rs"));
+    Ok(())
+  }
+
+  #[test]
+  fn multiple_main_templates_should_all_be_rendered() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("MULTIPLE_MAINS_tests")?;
+    save_file_content(tmp.path(), "source.yaml", "name: api")?;
+    save_file_content(tmp.path(), "_name.hbs", "{{name}}")?;
+    save_file_content(tmp.path(), "client.hbs", "### FILE client.txt\nclient {{> name}}\n### /FILE")?;
+    save_file_content(tmp.path(), "server.hbs", "### FILE server.txt\nserver {{> name}}\n### /FILE")?;
+    let root = tmp.path().to_string_lossy();
+    let options = Options {
+      source: format!("{root}/source.yaml"),
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      ..Default::default()
+    };
+
+    assert!(matches!(
+      generate(options.clone()),
+      Err(errors::CodegenrError::Rendering(render::RenderError::TwoMainTemp(_, _)))
+    ));
+
+    let generated = generate(Options {
+      multiple_main_templates: true,
+      ..options
+    })?;
+    assert_eq!(
+      generated.files,
+      [
+        ("client.txt".to_string(), "client api\n".to_string()),
+        ("server.txt".to_string(), "server api\n".to_string())
+      ]
+      .into_iter()
+      .collect()
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn run_one_with_backend_should_write_through_the_backend() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("BACKEND_tests")?;
+    save_file_content(tmp.path(), "source.yaml", "names: [a, b]")?;
+    save_file_content(
+      tmp.path(),
+      "main.hbs",
+      "### CLEAN *.txt\n{{#each names}}\n### FILE {{this}}.txt\n{{this}}\n### /FILE\n{{/each}}",
+    )?;
+    let root = tmp.path().to_string_lossy();
+
+    let backend = Rc::new(processor::MemoryBackend::new(
+      [("stale.txt".to_string(), "stale".to_string())].into_iter().collect(),
+    ));
+    run_one_codegenr_with_backend(
+      Options {
+        source: format!("{root}/source.yaml"),
+        output: format!("{root}/output"),
+        templates: vec![root.to_string()],
+        ..Default::default()
+      },
+      backend.clone(),
+    )?;
+
+    assert_eq!(
+      backend.files(),
+      [("a.txt".to_string(), "a\n".to_string()), ("b.txt".to_string(), "b\n".to_string())]
+        .into_iter()
+        .collect()
+    );
+    assert!(!tmp.path().join("output").exists());
+    Ok(())
+  }
+
+  #[test]
+  fn multiple_yaml_documents_should_be_rendered_as_one_source() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("YAML_DOCUMENTS_tests")?;
+    save_file_content(tmp.path(), "manifests.yaml", "kind: Deployment\n---\nkind: Service\n")?;
+    save_file_content(
+      tmp.path(),
+      "main.hbs",
+      "### CONSOLE\n{{#each this}}{{kind}} {{/each}}\n### /CONSOLE",
+    )?;
+    let root = tmp.path().to_string_lossy();
+
+    let generated = generate(Options {
+      source: format!("{root}/manifests.yaml"),
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      yaml_documents: loaders::YamlDocuments::Array,
+      ..Default::default()
+    })?;
+
+    assert_eq!(generated.console, vec!["Deployment Service ".to_string()]);
+    Ok(())
+  }
+
+  #[test]
+  fn sources_should_be_merged_in_one_model() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("SOURCES_tests")?;
+    save_file_content(
+      tmp.path(),
+      "main.hbs",
+      "### CONSOLE\n{{#each paths}}{{@key}} {{/each}}{{#each definitions}}{{@key}} {{/each}}\n### /CONSOLE",
+    )?;
+    let root = tmp.path().to_string_lossy();
+
+    let generated = generate(Options {
+      sources: vec!["_samples/merger/*.json".into()],
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      ..Default::default()
+    })?;
+
+    assert_eq!(generated.console, vec!["/foos /pets Foos Pet ".to_string()]);
+    Ok(())
+  }
+
+  #[test]
+  fn asyncapi_normalization_should_be_rendered() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("NORMALIZE_tests")?;
+    save_file_content(
+      tmp.path(),
+      "main.hbs",
+      "### CONSOLE\n{{#each x-codegenr.asyncapi.operations}}{{action}} {{operationId}} on {{address}}\n{{/each}}### /CONSOLE",
+    )?;
+    let root = tmp.path().to_string_lossy();
+
+    let generated = generate(Options {
+      source: "_samples/asyncapi/streetlights.v3.yaml".into(),
+      output: format!("{root}/output"),
+      templates: vec![root.to_string()],
+      normalize: vec![normalizer::Normalization::Asyncapi],
+      ..Default::default()
+    })?;
+
+    assert_eq!(
+      generated.console,
+      vec![
+        "receive receiveLightMeasurement on smartylighting/streetlights/{streetlightId}/lighting/measured".to_string(),
+        "send turnOn on smartylighting/streetlights/{streetlightId}/turn".to_string()
+      ]
+    );
+    Ok(())
+  }
+
+  fn failing_options() -> Options {
+    Options {
+      source: "_samples/not_existing.yaml".into(),
+      output: "_samples/not_existing_output".into(),
+      templates: vec![],
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn run_all_should_report_all_failing_sections() {
+    let options_map = OptionsMap::from_iter([("a".into(), failing_options()), ("b".into(), failing_options())]);
+    let errors = run_all_codegenr(options_map, false).expect_err("Should be an error");
+    let sections = errors.0.iter().map(|e| e.section.as_str()).collect::<Vec<_>>();
+    assert_eq!(sections, vec!["a", "b"]);
+    assert!(errors.0.iter().all(|e| e.error.kind() == "Resolving"));
+  }
+
+  #[test]
+  fn run_all_should_stop_at_first_failing_section_when_fail_fast() {
+    let options_map = OptionsMap::from_iter([("a".into(), failing_options()), ("b".into(), failing_options())]);
+    let errors = run_all_codegenr(options_map, true).expect_err("Should be an error");
+    let sections = errors.0.iter().map(|e| e.section.as_str()).collect::<Vec<_>>();
+    assert_eq!(sections, vec!["a"]);
+  }
+
+  #[test]
+  fn bundle_should_write_a_self_contained_yaml_file() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("BUNDLE_tests")?;
+    let output = tmp.path().join("petshop.yaml");
+    let output = output.to_str().expect("Temp path should be utf8");
+    bundle_codegenr("_samples/resolver/petshop_with_external.yaml", output, &Default::default())?;
+
+    let bundled = loaders::DocumentPath::parse(output)?.load_raw()?;
+    assert_eq!(
+      bundled["definitions"]["Pet"]["properties"]["category"]["$ref"],
+      "#/definitions/Category"
+    );
+    assert_eq!(
+      resolver::referenced_documents(&loaders::DocumentPath::parse(output)?, &Default::default(), &mut Default::default()).len(),
+      1
+    );
+    Ok(())
+  }
+}
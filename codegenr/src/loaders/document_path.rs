@@ -3,7 +3,7 @@ use serde_json::Value;
 use std::path::Path;
 use url::Url;
 
-use super::{json_from_string, FormatHint, LoaderError};
+use super::{json_from_string, remote::load_remote, yaml::YamlDocuments, FormatHint, LoadOptions, LoaderError};
 
 #[derive(Debug, PartialEq, Clone, Hash, Eq)]
 pub enum DocumentPath {
@@ -82,6 +82,8 @@ impl DocumentPath {
       FormatHint::Toml
     } else if s.ends_with(".graphql") || s.ends_with(".gql") {
       FormatHint::Graphql
+    } else if s.ends_with(".proto") {
+      FormatHint::Proto
     } else if s.ends_with(".xml") || s.ends_with(".xaml") || s.ends_with(".wsdl") || s.ends_with(".xsd") || s.ends_with(".xul") {
       FormatHint::Xml
     } else {
@@ -91,20 +93,21 @@ impl DocumentPath {
 
   #[allow(clippy::result_large_err)]
   pub fn load_raw(&self) -> Result<Value, LoaderError> {
+    self.load_raw_with(&Default::default())
+  }
+
+  /// Loads the document as set in the options: how the remote ones are downloaded, and how the yaml ones are read
+  #[allow(clippy::result_large_err)]
+  pub fn load_raw_with(&self, options: &LoadOptions) -> Result<Value, LoaderError> {
     let hint = self.guess_format();
-    match self {
-      DocumentPath::Url(url) => {
-        let body = reqwest::blocking::get(url.clone())
-          .map_err(|e| LoaderError::DownloadError(url.as_str().to_string(), e))?
-          .text()
-          .map_err(|e| LoaderError::DownloadError(url.as_str().to_string(), e))?;
-        json_from_string(&body, hint)
-      }
-      DocumentPath::FileName(file_name) => {
-        let content = std::fs::read_to_string(file_name).map_err(|e| LoaderError::Read(file_name.clone(), e))?;
-        json_from_string(&content, hint)
-      }
+    let content = match self {
+      DocumentPath::Url(url) => load_remote(url, options)?,
+      DocumentPath::FileName(file_name) => std::fs::read_to_string(file_name).map_err(|e| LoaderError::Read(file_name.clone(), e))?,
       DocumentPath::None => unreachable!("This is a non sense to try loading a 'None' document path."),
+    };
+    match hint {
+      FormatHint::Yaml if options.yaml_documents != YamlDocuments::Single => options.yaml_documents.load(&content),
+      _ => json_from_string(&content, hint),
     }
   }
 }
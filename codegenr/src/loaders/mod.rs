@@ -6,6 +6,10 @@ pub mod document_path;
 pub use document_path::*;
 pub mod graphql;
 pub mod json;
+pub mod proto;
+pub mod remote;
+pub use remote::{BasicAuth, HttpOptions, LoadOptions};
+pub use yaml::YamlDocuments;
 pub mod toml;
 pub mod xml;
 pub mod yaml;
@@ -26,6 +30,20 @@ pub enum LoaderError {
   Read(String, std::io::Error),
   #[error("Can't download file `{0}`: `{1}`.")]
   DownloadError(String, reqwest::Error),
+  #[error("`{0}` is not in the remote documents cache, and can't be downloaded offline.")]
+  NotCached(String),
+  #[error("`{0}` can't be loaded offline without a remote documents cache.")]
+  OfflineWithoutCache(String),
+  #[error("Environment variable `{0}` is not set.")]
+  MissingEnvVar(String),
+  #[error("Invalid `{0}` http header.")]
+  InvalidHeader(String),
+  #[error("Invalid `{0}` certificate: `{1}`.")]
+  Certificate(String, reqwest::Error),
+  #[error("Invalid `{0}` proxy: `{1}`.")]
+  Proxy(String, reqwest::Error),
+  #[error("Can't build the http client: `{0}`.")]
+  HttpClient(reqwest::Error),
   //
   // Path manipulation
   //
@@ -48,10 +66,14 @@ pub enum LoaderError {
   DeserialisationError {
     json_error: serde_json::Error,
     yaml_error: serde_yaml::Error,
-    toml_error: ::toml::de::Error,
-    xml_error: minidom::Error,
+    toml_error: Box<::toml::de::Error>,
+    xml_error: Box<minidom::Error>,
     graphql_error: graphql_parser::schema::ParseError,
   },
+  #[error("{documents} yaml documents can't be merged under {keys} keys.")]
+  YamlDocumentsKeys { keys: usize, documents: usize },
+  #[error("Unknown `{0}` yaml documents loading, expected `single`, `array` or `merge=<key1>,<key2>...`.")]
+  UnknownYamlDocuments(String),
   #[error("Yaml error: `{0}`.")]
   YamlError(#[from] serde_yaml::Error),
   #[error("Json error: `{0}`.")]
@@ -60,10 +82,14 @@ pub enum LoaderError {
   XmlError(#[from] minidom::Error),
   #[error("Graphql error: `{0}`.")]
   GraphqlError(#[from] graphql_parser::schema::ParseError),
+  #[error("Proto error: `{0}`.")]
+  ProtoError(#[from] proto::ProtoError),
   #[error("Did not try all the file loaders.")]
   DidNotTryAllFormats,
 }
 
+const BYTE_ORDER_MARK: char = '\u{feff}';
+
 #[derive(Debug, PartialEq, Copy, Clone, Hash, Eq)]
 pub(crate) enum FormatHint {
   /// The content should be json
@@ -76,6 +102,8 @@ pub(crate) enum FormatHint {
   Xml,
   /// The content should be a graphql schema
   Graphql,
+  /// The content should be a protocol buffers definition
+  Proto,
   /// We have no f.....g idea
   NoIdea,
 }
@@ -83,12 +111,14 @@ pub(crate) enum FormatHint {
 #[allow(clippy::result_large_err)]
 fn json_from_string(content: &str, hint: FormatHint) -> Result<Value, LoaderError> {
   use FormatHint::*;
+  let content = content.trim_start_matches(BYTE_ORDER_MARK);
   match hint {
     FormatHint::Json | FormatHint::NoIdea => try_loaders(content, &[Json, Yaml, Toml, Graphql]),
     FormatHint::Yaml => try_loaders(content, &[Yaml, Json, Toml, Xml, Graphql]),
     FormatHint::Toml => try_loaders(content, &[Toml, Json, Yaml, Xml, Graphql]),
     FormatHint::Xml => try_loaders(content, &[Xml, Json, Yaml, Toml, Graphql]),
     FormatHint::Graphql => try_loaders(content, &[Graphql, Json, Yaml, Toml, Xml]),
+    FormatHint::Proto => try_loaders(content, &[Proto]),
   }
 }
 
@@ -135,6 +165,8 @@ fn try_loaders(content: &str, formats: &[FormatHint]) -> Result<Value, LoaderErr
           },
         })
       }
+      // Nothing else looks like a .proto file, its own error is better than the other formats ones
+      FormatHint::Proto => return proto::ProtoLoader::json_from_str(content),
       FormatHint::NoIdea => todo!(),
     }
   }
@@ -142,8 +174,8 @@ fn try_loaders(content: &str, formats: &[FormatHint]) -> Result<Value, LoaderErr
   Err(LoaderError::DeserialisationError {
     json_error: json_error.ok_or(LoaderError::DidNotTryAllFormats)?,
     yaml_error: yaml_error.ok_or(LoaderError::DidNotTryAllFormats)?,
-    toml_error: toml_error.ok_or(LoaderError::DidNotTryAllFormats)?,
-    xml_error: xml_error.ok_or(LoaderError::DidNotTryAllFormats)?,
+    toml_error: Box::new(toml_error.ok_or(LoaderError::DidNotTryAllFormats)?),
+    xml_error: Box::new(xml_error.ok_or(LoaderError::DidNotTryAllFormats)?),
     graphql_error: graphql_error.ok_or(LoaderError::DidNotTryAllFormats)?,
   })
 }
@@ -221,6 +253,15 @@ mod tests {
     Ok(())
   }
 
+  #[test_case("\u{feff}{ \"value\": 42 }", FormatHint::Json)]
+  #[test_case("\u{feff}value: 42", FormatHint::Yaml)]
+  #[test_case("\u{feff}value = 42", FormatHint::Toml)]
+  #[test_case("\u{feff}value: 42", FormatHint::NoIdea)]
+  fn byte_order_mark_should_be_ignored(content: &str, hint: FormatHint) -> Result<(), LoaderError> {
+    assert_eq!(json_from_string(content, hint)?, serde_json::json!({ "value": 42 }));
+    Ok(())
+  }
+
   #[allow(clippy::result_large_err)]
   #[test]
   #[ignore]
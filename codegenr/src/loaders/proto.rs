@@ -0,0 +1,870 @@
+use super::{DocumentLoader, LoaderError};
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use thiserror::Error;
+
+/// Imports of these well known types are not `$ref`ed, as they are not files next to the `.proto` ones
+const WELL_KNOWN_IMPORTS: &str = "google/protobuf/";
+const LABELS: [&str; 3] = ["optional", "repeated", "required"];
+
+#[derive(Error, Debug)]
+#[error("Line {line}: {message}")]
+pub struct ProtoError {
+  pub line: usize,
+  pub message: String,
+}
+
+pub struct ProtoLoader {}
+impl DocumentLoader for ProtoLoader {
+  type Error = LoaderError;
+  fn json_from_str(content: &str) -> Result<Value, Self::Error> {
+    let proto = Parser::new(content)?.parse_file()?;
+    Ok(serde_json::to_value(&proto)?)
+  }
+}
+
+pub type ProtoOptions = Map<String, Value>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoFile {
+  pub syntax: String,
+  pub package: Option<String>,
+  pub imports: Vec<ProtoImport>,
+  pub options: ProtoOptions,
+  pub messages: Vec<ProtoMessage>,
+  pub enums: Vec<ProtoEnum>,
+  pub services: Vec<ProtoService>,
+}
+
+/// An imported file, `$ref`ed so the resolver loads it relatively to the importing one
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoImport {
+  pub path: String,
+  pub kind: ProtoImportKind,
+  #[serde(rename = "$ref", skip_serializing_if = "Option::is_none")]
+  pub reference: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtoImportKind {
+  Default,
+  Public,
+  Weak,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoMessage {
+  pub name: String,
+  #[serde(rename = "fullName")]
+  pub full_name: String,
+  /// All the fields, including the `oneof` ones
+  pub fields: Vec<ProtoField>,
+  pub oneofs: Vec<ProtoOneof>,
+  pub messages: Vec<ProtoMessage>,
+  pub enums: Vec<ProtoEnum>,
+  pub options: ProtoOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoField {
+  pub name: String,
+  pub number: i64,
+  /// The type as written, or `map` for map fields
+  #[serde(rename = "type")]
+  pub field_type: String,
+  pub label: Option<String>,
+  #[serde(rename = "keyType")]
+  pub key_type: Option<String>,
+  #[serde(rename = "valueType")]
+  pub value_type: Option<String>,
+  /// Name of the `oneof` this field is part of
+  pub oneof: Option<String>,
+  pub options: ProtoOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoOneof {
+  pub name: String,
+  pub fields: Vec<ProtoField>,
+  pub options: ProtoOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoEnum {
+  pub name: String,
+  #[serde(rename = "fullName")]
+  pub full_name: String,
+  pub values: Vec<ProtoEnumValue>,
+  pub options: ProtoOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoEnumValue {
+  pub name: String,
+  pub number: i64,
+  pub options: ProtoOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoService {
+  pub name: String,
+  #[serde(rename = "fullName")]
+  pub full_name: String,
+  pub rpcs: Vec<ProtoRpc>,
+  pub options: ProtoOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtoRpc {
+  pub name: String,
+  #[serde(rename = "inputType")]
+  pub input_type: String,
+  #[serde(rename = "outputType")]
+  pub output_type: String,
+  #[serde(rename = "clientStreaming")]
+  pub client_streaming: bool,
+  #[serde(rename = "serverStreaming")]
+  pub server_streaming: bool,
+  pub options: ProtoOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+  Ident,
+  Number,
+  Str,
+  Symbol,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+  kind: TokenKind,
+  /// The identifier, number or symbol, or the unescaped string
+  text: String,
+  line: usize,
+}
+
+fn tokenize(content: &str) -> Result<Vec<Token>, ProtoError> {
+  let chars = content.chars().collect::<Vec<_>>();
+  let at = |i: usize| chars.get(i).copied().unwrap_or_default();
+  let mut tokens = vec![];
+  let mut line = 1;
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    let start_line = line;
+    let (kind, text) = match c {
+      '\n' => {
+        line += 1;
+        i += 1;
+        continue;
+      }
+      c if c.is_whitespace() => {
+        i += 1;
+        continue;
+      }
+      '/' if at(i + 1) == '/' => {
+        while i < chars.len() && chars[i] != '\n' {
+          i += 1;
+        }
+        continue;
+      }
+      '/' if at(i + 1) == '*' => {
+        i += 2;
+        while i < chars.len() && !(chars[i] == '*' && at(i + 1) == '/') {
+          if chars[i] == '\n' {
+            line += 1;
+          }
+          i += 1;
+        }
+        if i >= chars.len() {
+          return Err(ProtoError::new(start_line, "Unterminated comment."));
+        }
+        i += 2;
+        continue;
+      }
+      '"' | '\'' => {
+        let (text, end) = unescape_string(&chars, i).ok_or_else(|| ProtoError::new(line, "Unterminated string."))?;
+        i = end;
+        (TokenKind::Str, text)
+      }
+      c if c.is_ascii_alphabetic() || c == '_' || (c == '.' && (at(i + 1).is_ascii_alphabetic() || at(i + 1) == '_')) => {
+        let start = i;
+        i += 1;
+        while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+          i += 1;
+        }
+        (TokenKind::Ident, chars[start..i].iter().collect())
+      }
+      c if c.is_ascii_digit() || (c == '.' && at(i + 1).is_ascii_digit()) => {
+        let start = i;
+        let hex = c == '0' && matches!(at(i + 1), 'x' | 'X');
+        i += 1;
+        while i < chars.len()
+          && (chars[i].is_ascii_alphanumeric()
+            || chars[i] == '.'
+            || (!hex && matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')))
+        {
+          i += 1;
+        }
+        (TokenKind::Number, chars[start..i].iter().collect())
+      }
+      c => {
+        i += 1;
+        (TokenKind::Symbol, c.to_string())
+      }
+    };
+    tokens.push(Token {
+      kind,
+      text,
+      line: start_line,
+    });
+  }
+  Ok(tokens)
+}
+
+/// Unescapes the string starting at the `start` quote, returning it with the index following its closing quote
+fn unescape_string(chars: &[char], start: usize) -> Option<(String, usize)> {
+  let quote = chars[start];
+  let mut text = String::new();
+  let mut i = start + 1;
+  loop {
+    match *chars.get(i)? {
+      c if c == quote => return Some((text, i + 1)),
+      '\n' => return None,
+      '\\' => {
+        i += 1;
+        let escaped = *chars.get(i)?;
+        let radix_digits = |i: usize, radix: u32, max: usize| {
+          let digits = chars[i..].iter().take(max).take_while(|c| c.is_digit(radix)).collect::<String>();
+          let value = u32::from_str_radix(&digits, radix).ok().and_then(char::from_u32);
+          (value, digits.len())
+        };
+        match escaped {
+          'n' => text.push('\n'),
+          't' => text.push('\t'),
+          'r' => text.push('\r'),
+          'x' | 'X' => {
+            let (value, len) = radix_digits(i + 1, 16, 2);
+            text.push(value?);
+            i += len;
+          }
+          '0'..='7' => {
+            let (value, len) = radix_digits(i, 8, 3);
+            text.push(value?);
+            i += len - 1;
+          }
+          c => text.push(c),
+        }
+      }
+      c => text.push(c),
+    }
+    i += 1;
+  }
+}
+
+impl ProtoError {
+  fn new(line: usize, message: impl Into<String>) -> Self {
+    Self {
+      line,
+      message: message.into(),
+    }
+  }
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  position: usize,
+}
+
+impl Parser {
+  fn new(content: &str) -> Result<Self, ProtoError> {
+    Ok(Self {
+      tokens: tokenize(content)?,
+      position: 0,
+    })
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position)
+  }
+
+  fn peek_text(&self) -> &str {
+    self.peek().map(|t| t.text.as_str()).unwrap_or_default()
+  }
+
+  fn error(&self, message: impl Into<String>) -> ProtoError {
+    let line = self.peek().or_else(|| self.tokens.last()).map(|t| t.line).unwrap_or(1);
+    ProtoError::new(line, message)
+  }
+
+  fn next(&mut self) -> Result<Token, ProtoError> {
+    let token = self.peek().cloned().ok_or_else(|| self.error("Unexpected end of file."))?;
+    self.position += 1;
+    Ok(token)
+  }
+
+  fn is(&self, kind: TokenKind, text: &str) -> bool {
+    matches!(self.peek(), Some(t) if t.kind == kind && t.text == text)
+  }
+
+  fn is_symbol(&self, symbol: &str) -> bool {
+    self.is(TokenKind::Symbol, symbol)
+  }
+
+  fn eat(&mut self, kind: TokenKind, text: &str) -> bool {
+    let found = self.is(kind, text);
+    if found {
+      self.position += 1;
+    }
+    found
+  }
+
+  fn expect(&mut self, kind: TokenKind, text: &str) -> Result<(), ProtoError> {
+    match self.eat(kind, text) {
+      true => Ok(()),
+      false => Err(self.error(format!("Expected `{}`, found `{}`.", text, self.peek_text()))),
+    }
+  }
+
+  fn expect_symbol(&mut self, symbol: &str) -> Result<(), ProtoError> {
+    self.expect(TokenKind::Symbol, symbol)
+  }
+
+  fn expect_kind(&mut self, kind: TokenKind, expected: &str) -> Result<String, ProtoError> {
+    match self.peek() {
+      Some(t) if t.kind == kind => Ok(self.next()?.text),
+      _ => Err(self.error(format!("Expected {}, found `{}`.", expected, self.peek_text()))),
+    }
+  }
+
+  fn expect_ident(&mut self) -> Result<String, ProtoError> {
+    self.expect_kind(TokenKind::Ident, "an identifier")
+  }
+
+  /// Adjacent strings are concatenated
+  fn expect_string(&mut self) -> Result<String, ProtoError> {
+    let mut text = self.expect_kind(TokenKind::Str, "a string")?;
+    while matches!(self.peek(), Some(t) if t.kind == TokenKind::Str) {
+      text.push_str(&self.next()?.text);
+    }
+    Ok(text)
+  }
+
+  fn expect_integer(&mut self) -> Result<i64, ProtoError> {
+    match self.parse_constant()? {
+      Value::Number(n) if n.is_i64() => Ok(n.as_i64().unwrap_or_default()),
+      value => Err(self.error(format!("Expected an integer, found `{}`.", value))),
+    }
+  }
+
+  fn parse_file(&mut self) -> Result<ProtoFile, ProtoError> {
+    let mut file = ProtoFile {
+      syntax: "proto2".into(),
+      package: None,
+      imports: vec![],
+      options: Default::default(),
+      messages: vec![],
+      enums: vec![],
+      services: vec![],
+    };
+    while let Some(token) = self.peek() {
+      match (token.kind, token.text.as_str()) {
+        (TokenKind::Ident, "syntax" | "edition") => {
+          self.next()?;
+          self.expect_symbol("=")?;
+          file.syntax = self.expect_string()?;
+          self.expect_symbol(";")?;
+        }
+        (TokenKind::Ident, "package") => {
+          self.next()?;
+          file.package = Some(self.expect_ident()?);
+          self.expect_symbol(";")?;
+        }
+        (TokenKind::Ident, "import") => {
+          self.next()?;
+          let kind = match self.peek_text() {
+            "public" => ProtoImportKind::Public,
+            "weak" => ProtoImportKind::Weak,
+            _ => ProtoImportKind::Default,
+          };
+          if kind != ProtoImportKind::Default {
+            self.next()?;
+          }
+          let path = self.expect_string()?;
+          self.expect_symbol(";")?;
+          let reference = Some(path.clone()).filter(|p| !p.starts_with(WELL_KNOWN_IMPORTS));
+          file.imports.push(ProtoImport { path, kind, reference });
+        }
+        (TokenKind::Ident, "option") => self.parse_option(&mut file.options)?,
+        (TokenKind::Ident, "message") => {
+          let message = self.parse_message(file.package.as_deref())?;
+          file.messages.push(message);
+        }
+        (TokenKind::Ident, "enum") => {
+          let proto_enum = self.parse_enum(file.package.as_deref())?;
+          file.enums.push(proto_enum);
+        }
+        (TokenKind::Ident, "service") => {
+          let service = self.parse_service(file.package.as_deref())?;
+          file.services.push(service);
+        }
+        (TokenKind::Ident, "extend") => self.skip_block()?,
+        (TokenKind::Symbol, ";") => self.position += 1,
+        _ => return Err(self.error(format!("Unexpected `{}`.", token.text))),
+      }
+    }
+    Ok(file)
+  }
+
+  /// Parses an `option name = value;` statement
+  fn parse_option(&mut self, options: &mut ProtoOptions) -> Result<(), ProtoError> {
+    self.expect(TokenKind::Ident, "option")?;
+    let name = self.parse_option_name()?;
+    self.expect_symbol("=")?;
+    let value = self.parse_constant()?;
+    self.expect_symbol(";")?;
+    options.insert(name, value);
+    Ok(())
+  }
+
+  /// Parses `name`, `(custom.name)` or `(custom.name).field` option names
+  fn parse_option_name(&mut self) -> Result<String, ProtoError> {
+    let mut name = match self.eat(TokenKind::Symbol, "(") {
+      true => {
+        let name = format!("({})", self.expect_ident()?);
+        self.expect_symbol(")")?;
+        name
+      }
+      false => self.expect_ident()?,
+    };
+    if matches!(self.peek(), Some(t) if t.kind == TokenKind::Ident && t.text.starts_with('.')) {
+      name.push_str(&self.next()?.text);
+    }
+    Ok(name)
+  }
+
+  /// Parses `[name = value, ...]` field options
+  fn parse_field_options(&mut self) -> Result<ProtoOptions, ProtoError> {
+    let mut options = ProtoOptions::new();
+    if !self.eat(TokenKind::Symbol, "[") {
+      return Ok(options);
+    }
+    loop {
+      let name = self.parse_option_name()?;
+      self.expect_symbol("=")?;
+      options.insert(name, self.parse_constant()?);
+      if !self.eat(TokenKind::Symbol, ",") {
+        break;
+      }
+    }
+    self.expect_symbol("]")?;
+    Ok(options)
+  }
+
+  fn parse_constant(&mut self) -> Result<Value, ProtoError> {
+    let token = self.peek().cloned().ok_or_else(|| self.error("Unexpected end of file."))?;
+    match (token.kind, token.text.as_str()) {
+      (TokenKind::Str, _) => Ok(Value::String(self.expect_string()?)),
+      (TokenKind::Symbol, sign @ ("-" | "+")) => {
+        let negative = sign == "-";
+        self.next()?;
+        match self.peek() {
+          Some(t) if t.kind == TokenKind::Number => {
+            let number = self.next()?;
+            self.number(&number.text, negative)
+          }
+          Some(t) if t.kind == TokenKind::Ident && matches!(t.text.as_str(), "inf" | "nan") => {
+            Ok(Value::String(format!("{}{}", sign, self.next()?.text)))
+          }
+          _ => Err(self.error(format!("Expected a number, found `{}`.", self.peek_text()))),
+        }
+      }
+      (TokenKind::Number, text) => {
+        let text = text.to_string();
+        self.next()?;
+        self.number(&text, false)
+      }
+      (TokenKind::Ident, "true") => self.next().map(|_| Value::Bool(true)),
+      (TokenKind::Ident, "false") => self.next().map(|_| Value::Bool(false)),
+      (TokenKind::Ident, _) => self.next().map(|t| Value::String(t.text)),
+      (TokenKind::Symbol, "{") => {
+        self.next()?;
+        self.parse_aggregate("}")
+      }
+      _ => Err(self.error(format!("Expected a constant, found `{}`.", token.text))),
+    }
+  }
+
+  fn number(&self, text: &str, negative: bool) -> Result<Value, ProtoError> {
+    let invalid = || self.error(format!("Invalid `{}` number.", text));
+    let sign = if negative { -1 } else { 1 };
+    let integer = |digits: &str, radix| i64::from_str_radix(digits, radix).ok().map(|i| Value::from(sign * i));
+    let lower = text.to_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x") {
+      integer(hex, 16)
+    } else if lower.contains(['.', 'e']) {
+      let float = lower.trim_end_matches('f').parse::<f64>().ok();
+      float.and_then(|f| Number::from_f64(sign as f64 * f)).map(Value::Number)
+    } else if lower.len() > 1 && lower.starts_with('0') {
+      integer(&lower[1..], 8)
+    } else {
+      integer(&lower, 10).or_else(|| lower.parse::<u64>().ok().filter(|_| !negative).map(Value::from))
+    };
+    value.ok_or_else(invalid)
+  }
+
+  /// Parses a text format message value, until its `close` symbol.
+  /// Repeated keys are collected in arrays.
+  fn parse_aggregate(&mut self, close: &str) -> Result<Value, ProtoError> {
+    let mut map = Map::new();
+    while !self.eat(TokenKind::Symbol, close) {
+      let key = if self.eat(TokenKind::Symbol, "[") {
+        let key = format!("[{}]", self.expect_ident()?);
+        self.expect_symbol("]")?;
+        key
+      } else {
+        self.expect_ident()?
+      };
+      self.eat(TokenKind::Symbol, ":");
+      let value = self.parse_aggregate_value()?;
+      match map.get_mut(&key) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+        None => {
+          map.insert(key, value);
+        }
+      }
+      if !self.eat(TokenKind::Symbol, ",") {
+        self.eat(TokenKind::Symbol, ";");
+      }
+    }
+    Ok(Value::Object(map))
+  }
+
+  fn parse_aggregate_value(&mut self) -> Result<Value, ProtoError> {
+    if self.eat(TokenKind::Symbol, "<") {
+      return self.parse_aggregate(">");
+    }
+    if self.eat(TokenKind::Symbol, "[") {
+      let mut values = vec![];
+      while !self.eat(TokenKind::Symbol, "]") {
+        values.push(self.parse_aggregate_value()?);
+        self.eat(TokenKind::Symbol, ",");
+      }
+      return Ok(Value::Array(values));
+    }
+    self.parse_constant()
+  }
+
+  fn parse_message(&mut self, scope: Option<&str>) -> Result<ProtoMessage, ProtoError> {
+    self.expect(TokenKind::Ident, "message")?;
+    let name = self.expect_ident()?;
+    let mut message = ProtoMessage {
+      full_name: full_name(scope, &name),
+      name,
+      fields: vec![],
+      oneofs: vec![],
+      messages: vec![],
+      enums: vec![],
+      options: Default::default(),
+    };
+    self.expect_symbol("{")?;
+    while !self.eat(TokenKind::Symbol, "}") {
+      match self.peek_text() {
+        "message" => {
+          let nested = self.parse_message(Some(&message.full_name))?;
+          message.messages.push(nested);
+        }
+        "enum" => {
+          let nested = self.parse_enum(Some(&message.full_name))?;
+          message.enums.push(nested);
+        }
+        "option" => self.parse_option(&mut message.options)?,
+        "oneof" => self.parse_oneof(&mut message)?,
+        "reserved" | "extensions" => self.skip_statement()?,
+        "extend" => self.skip_block()?,
+        ";" => self.position += 1,
+        _ => {
+          let field = self.parse_field(None)?;
+          message.fields.push(field);
+        }
+      }
+    }
+    Ok(message)
+  }
+
+  fn parse_field(&mut self, oneof: Option<&str>) -> Result<ProtoField, ProtoError> {
+    let label = match LABELS.contains(&self.peek_text()) && oneof.is_none() {
+      true => Some(self.next()?.text),
+      false => None,
+    };
+    let field_type = self.expect_ident()?;
+    let (field_type, key_type, value_type) = match field_type.as_str() {
+      "map" if self.eat(TokenKind::Symbol, "<") => {
+        let key_type = self.expect_ident()?;
+        self.expect_symbol(",")?;
+        let value_type = self.expect_ident()?;
+        self.expect_symbol(">")?;
+        (field_type, Some(key_type), Some(value_type))
+      }
+      "group" => return Err(self.error("Groups are not supported, use nested messages.")),
+      _ => (field_type, None, None),
+    };
+    let name = self.expect_ident()?;
+    self.expect_symbol("=")?;
+    let number = self.expect_integer()?;
+    let options = self.parse_field_options()?;
+    self.expect_symbol(";")?;
+    Ok(ProtoField {
+      name,
+      number,
+      field_type,
+      label,
+      key_type,
+      value_type,
+      oneof: oneof.map(Into::into),
+      options,
+    })
+  }
+
+  fn parse_oneof(&mut self, message: &mut ProtoMessage) -> Result<(), ProtoError> {
+    self.expect(TokenKind::Ident, "oneof")?;
+    let mut oneof = ProtoOneof {
+      name: self.expect_ident()?,
+      fields: vec![],
+      options: Default::default(),
+    };
+    self.expect_symbol("{")?;
+    while !self.eat(TokenKind::Symbol, "}") {
+      match self.peek_text() {
+        "option" => self.parse_option(&mut oneof.options)?,
+        ";" => self.position += 1,
+        _ => {
+          let field = self.parse_field(Some(&oneof.name))?;
+          oneof.fields.push(field.clone());
+          message.fields.push(field);
+        }
+      }
+    }
+    message.oneofs.push(oneof);
+    Ok(())
+  }
+
+  fn parse_enum(&mut self, scope: Option<&str>) -> Result<ProtoEnum, ProtoError> {
+    self.expect(TokenKind::Ident, "enum")?;
+    let name = self.expect_ident()?;
+    let mut proto_enum = ProtoEnum {
+      full_name: full_name(scope, &name),
+      name,
+      values: vec![],
+      options: Default::default(),
+    };
+    self.expect_symbol("{")?;
+    while !self.eat(TokenKind::Symbol, "}") {
+      match self.peek_text() {
+        "option" => self.parse_option(&mut proto_enum.options)?,
+        "reserved" => self.skip_statement()?,
+        ";" => self.position += 1,
+        _ => {
+          let name = self.expect_ident()?;
+          self.expect_symbol("=")?;
+          let number = self.expect_integer()?;
+          let options = self.parse_field_options()?;
+          self.expect_symbol(";")?;
+          proto_enum.values.push(ProtoEnumValue { name, number, options });
+        }
+      }
+    }
+    Ok(proto_enum)
+  }
+
+  fn parse_service(&mut self, scope: Option<&str>) -> Result<ProtoService, ProtoError> {
+    self.expect(TokenKind::Ident, "service")?;
+    let name = self.expect_ident()?;
+    let mut service = ProtoService {
+      full_name: full_name(scope, &name),
+      name,
+      rpcs: vec![],
+      options: Default::default(),
+    };
+    self.expect_symbol("{")?;
+    while !self.eat(TokenKind::Symbol, "}") {
+      match self.peek_text() {
+        "option" => self.parse_option(&mut service.options)?,
+        "rpc" => {
+          let rpc = self.parse_rpc()?;
+          service.rpcs.push(rpc);
+        }
+        ";" => self.position += 1,
+        _ => return Err(self.error(format!("Unexpected `{}` in service.", self.peek_text()))),
+      }
+    }
+    Ok(service)
+  }
+
+  fn parse_rpc(&mut self) -> Result<ProtoRpc, ProtoError> {
+    self.expect(TokenKind::Ident, "rpc")?;
+    let name = self.expect_ident()?;
+    let (client_streaming, input_type) = self.parse_rpc_type()?;
+    self.expect(TokenKind::Ident, "returns")?;
+    let (server_streaming, output_type) = self.parse_rpc_type()?;
+    let mut options = ProtoOptions::new();
+    if self.eat(TokenKind::Symbol, "{") {
+      while !self.eat(TokenKind::Symbol, "}") {
+        match self.peek_text() {
+          ";" => self.position += 1,
+          _ => self.parse_option(&mut options)?,
+        }
+      }
+    } else {
+      self.expect_symbol(";")?;
+    }
+    Ok(ProtoRpc {
+      name,
+      input_type,
+      output_type,
+      client_streaming,
+      server_streaming,
+      options,
+    })
+  }
+
+  /// Parses `(Type)` or `(stream Type)`
+  fn parse_rpc_type(&mut self) -> Result<(bool, String), ProtoError> {
+    self.expect_symbol("(")?;
+    let mut rpc_type = self.expect_ident()?;
+    let streaming = rpc_type == "stream" && !self.is_symbol(")");
+    if streaming {
+      rpc_type = self.expect_ident()?;
+    }
+    self.expect_symbol(")")?;
+    Ok((streaming, rpc_type))
+  }
+
+  fn skip_statement(&mut self) -> Result<(), ProtoError> {
+    while self.next()?.text != ";" {}
+    Ok(())
+  }
+
+  fn skip_block(&mut self) -> Result<(), ProtoError> {
+    while !self.eat(TokenKind::Symbol, "{") {
+      self.next()?;
+    }
+    let mut depth = 1;
+    while depth > 0 {
+      let token = self.next()?;
+      match (token.kind, token.text.as_str()) {
+        (TokenKind::Symbol, "{") => depth += 1,
+        (TokenKind::Symbol, "}") => depth -= 1,
+        _ => {}
+      }
+    }
+    Ok(())
+  }
+}
+
+fn full_name(scope: Option<&str>, name: &str) -> String {
+  match scope {
+    Some(scope) => format!("{scope}.{name}"),
+    None => name.into(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::resolver::resolve_sample;
+  use serde_json::json;
+  use test_case::test_case;
+
+  #[test]
+  fn proto_file_should_be_loaded_as_json() -> Result<(), anyhow::Error> {
+    let content = std::fs::read_to_string("_samples/proto/shop.proto")?;
+    let json = ProtoLoader::json_from_str(&content)?;
+
+    assert_eq!(json["syntax"], "proto3");
+    assert_eq!(json["package"], "shop.v1");
+    assert_eq!(json["options"]["go_package"], "example.com/shop/v1;shopv1");
+    assert_eq!(
+      json["imports"],
+      json!([
+        { "path": "common.proto", "kind": "default", "$ref": "common.proto" },
+        { "path": "google/protobuf/timestamp.proto", "kind": "public" }
+      ])
+    );
+
+    let product = &json["messages"][0];
+    assert_eq!(product["fullName"], "shop.v1.Product");
+    let fields = product["fields"]
+      .as_array()
+      .map(|f| f.iter().map(|f| f["name"].clone()).collect::<Vec<_>>());
+    assert_eq!(
+      fields,
+      Some(vec![
+        json!("id"),
+        json!("name"),
+        json!("tags"),
+        json!("prices"),
+        json!("status"),
+        json!("created_at"),
+        json!("quantity"),
+        json!("unlimited")
+      ])
+    );
+    assert_eq!(product["fields"][1]["options"], json!({ "json_name": "displayName" }));
+    assert_eq!(product["fields"][2]["label"], "repeated");
+    assert_eq!(
+      (
+        &product["fields"][3]["type"],
+        &product["fields"][3]["keyType"],
+        &product["fields"][3]["valueType"]
+      ),
+      (&json!("map"), &json!("string"), &json!("Price"))
+    );
+    assert_eq!(product["fields"][5]["type"], "google.protobuf.Timestamp");
+    assert_eq!(product["fields"][6]["oneof"], "stock");
+    assert_eq!(product["oneofs"][0]["fields"][1]["number"], 8);
+    assert_eq!(product["enums"][0]["fullName"], "shop.v1.Product.Status");
+    assert_eq!(product["enums"][0]["values"][2]["options"]["deprecated"], true);
+    assert_eq!(product["messages"][0]["fullName"], "shop.v1.Product.Variant");
+
+    let service = &json["services"][0];
+    assert_eq!(service["options"]["deprecated"], false);
+    assert_eq!(
+      service["rpcs"][0]["options"]["(google.api.http)"],
+      json!({ "get": "/v1/products/{id}", "additional_bindings": { "get": "/v1/catalog/{id}" } })
+    );
+    assert_eq!(
+      (&service["rpcs"][1]["clientStreaming"], &service["rpcs"][1]["serverStreaming"]),
+      (&json!(true), &json!(true))
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn proto_imports_should_be_resolved_relatively_to_the_file() -> Result<(), anyhow::Error> {
+    let json = resolve_sample("_samples/proto/shop.proto")?;
+    assert_eq!(json["imports"][0]["messages"][0]["fullName"], "shop.v1.Price");
+    Ok(())
+  }
+
+  #[test_case("enum E { A = -1; B = 0x1F; C = 017; }", json!([-1, 31, 15]))]
+  fn enum_values_numbers_tests(content: &str, expected: Value) -> Result<(), anyhow::Error> {
+    let json = ProtoLoader::json_from_str(content)?;
+    let numbers = json["enums"][0]["values"]
+      .as_array()
+      .map(|v| v.iter().map(|v| v["number"].clone()).collect::<Vec<_>>());
+    assert_eq!(numbers.map(Value::Array), Some(expected));
+    Ok(())
+  }
+
+  #[test_case("message A { string a = 1 }", "Line 1: Expected `;`, found `}`.")]
+  #[test_case("message A {\n  string a = one;\n}", "Line 2: Expected an integer, found `\"one\"`.")]
+  #[test_case("message A {\n /* unterminated", "Line 2: Unterminated comment.")]
+  fn proto_errors_tests(content: &str, expected: &str) {
+    let error = Parser::new(content).and_then(|mut p| p.parse_file()).expect_err("Should fail");
+    assert_eq!(error.to_string(), expected);
+  }
+}
@@ -0,0 +1,434 @@
+use super::{yaml::YamlDocuments, LoaderError};
+use reqwest::{
+  blocking::{Client, RequestBuilder, Response},
+  header::{HeaderMap, HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+  Certificate, Proxy, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+  collections::{BTreeMap, HashMap},
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+use tracing::{debug, warn};
+use url::Url;
+
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+static ENV_VAR_REGEX: once_cell::sync::Lazy<regex::Regex> =
+  once_cell::sync::Lazy::new(|| regex::Regex::new(r"\$\{([^}]+)\}").expect("The ENV_VAR_REGEX regex did not compile."));
+
+/// The http clients already built, by http options, so their connections are reused
+static CLIENTS: once_cell::sync::Lazy<Mutex<HashMap<HttpOptions, Arc<Client>>>> = once_cell::sync::Lazy::new(Default::default);
+
+/// Gives the value of an environment variable
+type EnvLookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// How the documents are loaded
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct LoadOptions {
+  /// Folder where the downloaded documents are kept, and revalidated with their `ETag` or `Last-Modified` headers
+  pub remote_cache: Option<PathBuf>,
+  /// If set, only the documents of the remote cache are used, and nothing is downloaded
+  pub offline: bool,
+  pub http: HttpOptions,
+  /// How the yaml documents holding several `---` separated documents are read
+  pub yaml_documents: YamlDocuments,
+}
+
+/// How the remote documents are requested.
+/// The headers values, the token and the password can use `${ENV_VAR}` environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpOptions {
+  #[serde(default)]
+  pub headers: BTreeMap<String, String>,
+  pub bearer_token: Option<String>,
+  pub basic_auth: Option<BasicAuth>,
+  pub timeout_secs: Option<u64>,
+  /// How many times a request failing to connect, or with a `5XX` status, is sent again
+  #[serde(default)]
+  pub retries: u32,
+  /// Paths to PEM certificates of custom certificate authorities
+  #[serde(default)]
+  pub ca_certificates: Vec<String>,
+  /// Url of the proxy all the requests go through, eg: `http://proxy.internal:3128`
+  pub proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BasicAuth {
+  pub username: String,
+  pub password: Option<String>,
+}
+
+impl HttpOptions {
+  /// The http client of these options, built once
+  fn client(&self) -> Result<Arc<Client>, LoaderError> {
+    let mut clients = CLIENTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(client) = clients.get(self) {
+      return Ok(client.clone());
+    }
+
+    let mut builder = Client::builder();
+    if let Some(timeout) = self.timeout_secs {
+      builder = builder.timeout(Duration::from_secs(timeout));
+    }
+    for path in self.ca_certificates.iter() {
+      let pem = std::fs::read(path).map_err(|e| LoaderError::Read(path.clone(), e))?;
+      let certificate = Certificate::from_pem(&pem).map_err(|e| LoaderError::Certificate(path.clone(), e))?;
+      builder = builder.add_root_certificate(certificate);
+    }
+    if let Some(proxy) = &self.proxy {
+      builder = builder.proxy(Proxy::all(proxy).map_err(|e| LoaderError::Proxy(proxy.clone(), e))?);
+    }
+    let client = Arc::new(builder.build().map_err(LoaderError::HttpClient)?);
+    clients.insert(self.clone(), client.clone());
+    Ok(client)
+  }
+
+  /// Sets the headers and the authentication of a request, their environment variables being expanded
+  fn prepare(&self, request: RequestBuilder, env: EnvLookup) -> Result<RequestBuilder, LoaderError> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in self.headers.iter() {
+      let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| LoaderError::InvalidHeader(name.clone()))?;
+      let header_value = HeaderValue::from_str(&expand_env_vars(value, env)?).map_err(|_| LoaderError::InvalidHeader(name.clone()))?;
+      headers.insert(header_name, header_value);
+    }
+    let request = request.headers(headers);
+
+    let request = match &self.basic_auth {
+      Some(auth) => {
+        let password = auth.password.as_deref().map(|p| expand_env_vars(p, env)).transpose()?;
+        request.basic_auth(expand_env_vars(&auth.username, env)?, password)
+      }
+      None => request,
+    };
+    Ok(match &self.bearer_token {
+      Some(token) => request.bearer_auth(expand_env_vars(token, env)?),
+      None => request,
+    })
+  }
+
+  /// Sends the request, and sends it again up to `retries` times if it fails to connect or gets a server error
+  fn send(&self, url: &Url, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    let mut attempt = 0;
+    loop {
+      let result = request.try_clone().expect("A request without body can be cloned").send();
+      let failed = match &result {
+        Ok(response) => response.status().is_server_error(),
+        Err(e) => e.is_connect() || e.is_timeout(),
+      };
+      if !failed || attempt >= self.retries {
+        return result;
+      }
+      attempt += 1;
+      warn!("Request to `{}` failed, retrying ({}/{}).", url, attempt, self.retries);
+      std::thread::sleep(RETRY_DELAY);
+    }
+  }
+}
+
+/// Replaces the `${ENV_VAR}` parts of a value with the environment variables values
+fn expand_env_vars(value: &str, env: EnvLookup) -> Result<String, LoaderError> {
+  let mut expanded = String::with_capacity(value.len());
+  let mut last = 0;
+  for captures in ENV_VAR_REGEX.captures_iter(value) {
+    let (all, name) = (captures.get(0).expect("Group 0 is the whole match"), &captures[1]);
+    expanded.push_str(&value[last..all.start()]);
+    expanded.push_str(&env(name).ok_or_else(|| LoaderError::MissingEnvVar(name.into()))?);
+    last = all.end();
+  }
+  expanded.push_str(&value[last..]);
+  Ok(expanded)
+}
+
+/// A downloaded document, as stored in the remote cache
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedDocument {
+  url: String,
+  etag: Option<String>,
+  last_modified: Option<String>,
+  body: String,
+}
+
+/// Downloads a remote document, or gets it from the remote cache
+#[::tracing::instrument(level = "trace")]
+pub(crate) fn load_remote(url: &Url, options: &LoadOptions) -> Result<String, LoaderError> {
+  load_remote_with(url, options, &|name| std::env::var(name).ok())
+}
+
+fn load_remote_with(url: &Url, options: &LoadOptions, env: EnvLookup) -> Result<String, LoaderError> {
+  let cache_file = options.remote_cache.as_deref().map(|folder| cache_file_path(folder, url));
+  let cached = cache_file.as_deref().and_then(|cache_file| read_cached(url, cache_file));
+
+  if options.offline {
+    return match (cache_file, cached) {
+      (None, _) => Err(LoaderError::OfflineWithoutCache(url.to_string())),
+      (Some(_), None) => Err(LoaderError::NotCached(url.to_string())),
+      (Some(_), Some(cached)) => Ok(cached.body),
+    };
+  }
+
+  let download_error = |e| LoaderError::DownloadError(url.to_string(), e);
+  let mut request = options.http.prepare(options.http.client()?.get(url.clone()), env)?;
+  if let Some(cached) = &cached {
+    if let Some(etag) = &cached.etag {
+      request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &cached.last_modified {
+      request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+  }
+  let response = options.http.send(url, request).map_err(download_error)?;
+
+  if let Some(cached) = cached {
+    if response.status() == StatusCode::NOT_MODIFIED {
+      debug!("`{}` was not modified, the cached copy is used.", url);
+      return Ok(cached.body);
+    }
+  }
+
+  let response = response.error_for_status().map_err(download_error)?;
+  let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(String::from);
+  let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+  let body = response.text().map_err(download_error)?;
+
+  if let Some(cache_file) = cache_file {
+    let cached = CachedDocument {
+      url: url.to_string(),
+      etag,
+      last_modified,
+      body,
+    };
+    write_cached(&cache_file, &cached)?;
+    return Ok(cached.body);
+  }
+  Ok(body)
+}
+
+/// Each url has its own file, named after the sha256 of the url so long urls still fit in a file name
+fn cache_file_path(folder: &Path, url: &Url) -> PathBuf {
+  folder.join(format!("{:x}.json", Sha256::digest(url.as_str())))
+}
+
+fn read_cached(url: &Url, cache_file: &Path) -> Option<CachedDocument> {
+  let content = std::fs::read_to_string(cache_file).ok()?;
+  let cached: CachedDocument = serde_json::from_str(&content).ok()?;
+  (cached.url == url.as_str()).then_some(cached)
+}
+
+fn write_cached(cache_file: &Path, cached: &CachedDocument) -> Result<(), LoaderError> {
+  if let Some(folder) = cache_file.parent() {
+    std::fs::create_dir_all(folder)?;
+  }
+  std::fs::write(cache_file, serde_json::to_string(cached)?)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+  };
+  use tempdir::TempDir;
+
+  type Requests = Arc<Mutex<Vec<Vec<String>>>>;
+
+  /// A local http server answering each request with the response built from its headers and index,
+  /// recording the received requests headers
+  fn serve_with(respond: impl Fn(&[String], usize) -> String + Send + 'static) -> (Url, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Should bind a local port");
+    let url = Url::parse(&format!(
+      "http://{}/api.yaml",
+      listener.local_addr().expect("Should have an address")
+    ))
+    .expect("Should parse");
+    let requests = Arc::new(Mutex::new(vec![]));
+    let recorded = requests.clone();
+    std::thread::spawn(move || {
+      for (index, mut stream) in listener.incoming().flatten().enumerate() {
+        let headers = BufReader::new(&stream)
+          .lines()
+          .map_while(Result::ok)
+          .take_while(|l| !l.is_empty())
+          .collect::<Vec<_>>();
+        let response = respond(&headers, index);
+        recorded.lock().expect("Should lock").push(headers);
+        let _ = stream.write_all(response.as_bytes());
+      }
+    });
+    (url, requests)
+  }
+
+  fn ok(body: &str, etag: &str) -> String {
+    format!(
+      "HTTP/1.1 200 OK\r\nETag: {etag}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+      body.len()
+    )
+  }
+
+  /// Serves a static document, answering `304` to the requests revalidating its etag
+  fn serve(body: &'static str, etag: &'static str) -> (Url, Requests) {
+    serve_with(move |headers, _| {
+      if headers.iter().any(|h| h.to_lowercase() == format!("if-none-match: {etag}")) {
+        format!("HTTP/1.1 304 Not Modified\r\nETag: {etag}\r\nConnection: close\r\n\r\n")
+      } else {
+        ok(body, etag)
+      }
+    })
+  }
+
+  #[test]
+  fn remote_documents_should_be_cached_and_revalidated() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("REMOTE_CACHE_tests")?;
+    let (url, requests) = serve("hello: world", "\"v1\"");
+    let options = LoadOptions {
+      remote_cache: Some(tmp.path().to_path_buf()),
+      ..Default::default()
+    };
+
+    assert_eq!(load_remote(&url, &options)?, "hello: world");
+    assert_eq!(load_remote(&url, &options)?, "hello: world");
+
+    let requests = requests.lock().expect("Should lock");
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[0].iter().any(|h| h.to_lowercase().starts_with("if-none-match")));
+    assert!(requests[1].iter().any(|h| h.to_lowercase() == "if-none-match: \"v1\""));
+    Ok(())
+  }
+
+  #[test]
+  fn long_urls_should_be_cached() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("REMOTE_CACHE_tests")?;
+    let (url, requests) = serve("hello: world", "\"v1\"");
+    let url = Url::parse(&format!("{url}?filter={}", "a".repeat(300)))?;
+    let options = LoadOptions {
+      remote_cache: Some(tmp.path().to_path_buf()),
+      ..Default::default()
+    };
+
+    assert_eq!(load_remote(&url, &options)?, "hello: world");
+    let cache_file = cache_file_path(tmp.path(), &url);
+    assert!(cache_file.exists());
+    assert_eq!(read_cached(&url, &cache_file).map(|c| c.url), Some(url.to_string()));
+    assert_eq!(load_remote(&url, &LoadOptions { offline: true, ..options })?, "hello: world");
+    assert_eq!(requests.lock().expect("Should lock").len(), 1);
+    Ok(())
+  }
+
+  #[test]
+  fn offline_mode_should_only_use_the_cache() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("REMOTE_CACHE_tests")?;
+    let (url, requests) = serve("hello: world", "\"v1\"");
+    let online = LoadOptions {
+      remote_cache: Some(tmp.path().to_path_buf()),
+      ..Default::default()
+    };
+    let offline = LoadOptions {
+      offline: true,
+      ..online.clone()
+    };
+
+    assert!(matches!(load_remote(&url, &offline), Err(LoaderError::NotCached(_))));
+    load_remote(&url, &online)?;
+    assert_eq!(load_remote(&url, &offline)?, "hello: world");
+    assert_eq!(requests.lock().expect("Should lock").len(), 1);
+
+    let without_cache = LoadOptions {
+      offline: true,
+      ..Default::default()
+    };
+    assert!(matches!(
+      load_remote(&url, &without_cache),
+      Err(LoaderError::OfflineWithoutCache(_))
+    ));
+    Ok(())
+  }
+
+  #[test]
+  fn http_options_should_set_headers_and_auth() -> Result<(), anyhow::Error> {
+    let env = |name: &str| (name == "CODEGENR_REMOTE_TESTS_KEY").then(|| "secret".to_string());
+    let (url, requests) = serve("hello: world", "\"v1\"");
+    let options = LoadOptions {
+      http: HttpOptions {
+        headers: BTreeMap::from_iter([("X-Api-Key".to_string(), "key-${CODEGENR_REMOTE_TESTS_KEY}".to_string())]),
+        bearer_token: Some("${CODEGENR_REMOTE_TESTS_KEY}".into()),
+        timeout_secs: Some(5),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+
+    load_remote_with(&url, &options, &env)?;
+    let requests = requests.lock().expect("Should lock");
+    let headers = requests[0].iter().map(|h| h.to_lowercase()).collect::<Vec<_>>();
+    assert!(headers.contains(&"x-api-key: key-secret".to_string()));
+    assert!(headers.contains(&"authorization: bearer secret".to_string()));
+    Ok(())
+  }
+
+  #[test]
+  fn http_options_should_retry_server_errors() -> Result<(), anyhow::Error> {
+    let (url, requests) = serve_with(|_, index| match index {
+      0 | 1 => "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".into(),
+      _ => ok("hello: world", "\"v1\""),
+    });
+    let mut options = LoadOptions::default();
+    assert!(load_remote(&url, &options).is_err());
+
+    options.http.retries = 1;
+    assert_eq!(load_remote(&url, &options)?, "hello: world");
+    assert_eq!(requests.lock().expect("Should lock").len(), 3);
+    Ok(())
+  }
+
+  #[test]
+  fn http_options_should_go_through_the_proxy() -> Result<(), anyhow::Error> {
+    let (proxy, requests) = serve("hello: world", "\"v1\"");
+    let options = LoadOptions {
+      http: HttpOptions {
+        proxy: Some(proxy.origin().ascii_serialization()),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+
+    let url = Url::parse("http://api.example.invalid/api.yaml")?;
+    assert_eq!(load_remote(&url, &options)?, "hello: world");
+    let requests = requests.lock().expect("Should lock");
+    assert_eq!(requests[0][0], "GET http://api.example.invalid/api.yaml HTTP/1.1");
+    Ok(())
+  }
+
+  #[test]
+  fn http_clients_should_be_built_once_per_options() -> Result<(), anyhow::Error> {
+    let options = HttpOptions {
+      timeout_secs: Some(42),
+      ..Default::default()
+    };
+    let other = HttpOptions {
+      timeout_secs: Some(43),
+      ..Default::default()
+    };
+    let client = options.client()?;
+    assert!(Arc::ptr_eq(&client, &options.client()?));
+    assert!(Arc::ptr_eq(&client, &options.clone().client()?));
+    assert!(!Arc::ptr_eq(&client, &other.client()?));
+    Ok(())
+  }
+
+  #[test]
+  fn missing_env_vars_should_fail() {
+    assert!(matches!(
+      expand_env_vars("${CODEGENR_REMOTE_TESTS_MISSING}", &|_| None),
+      Err(LoaderError::MissingEnvVar(name)) if name == "CODEGENR_REMOTE_TESTS_MISSING"
+    ));
+  }
+}
@@ -1,5 +1,7 @@
-use super::DocumentLoader;
-use serde_json::Value;
+use super::{DocumentLoader, LoaderError};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::str::FromStr;
 
 pub struct YamlLoader {}
 impl DocumentLoader for YamlLoader {
@@ -8,3 +10,101 @@ impl DocumentLoader for YamlLoader {
     serde_yaml::from_str(content)
   }
 }
+
+impl YamlLoader {
+  /// Loads all the `---` separated documents of a yaml content
+  pub fn documents_from_str(content: &str) -> Result<Vec<Value>, serde_yaml::Error> {
+    serde_yaml::Deserializer::from_str(content).map(Value::deserialize).collect()
+  }
+}
+
+/// How a yaml file holding several `---` separated documents is loaded.
+/// A file holding a single document is always loaded as this document.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum YamlDocuments {
+  /// Fails, as only one document is expected
+  #[default]
+  Single,
+  /// Loads the documents as an array
+  Array,
+  /// Loads the documents as an object, each one under the key having its position
+  Merge(Vec<String>),
+}
+
+impl YamlDocuments {
+  /// Loads a yaml content as set
+  pub fn load(&self, content: &str) -> Result<Value, LoaderError> {
+    if *self == YamlDocuments::Single {
+      return Ok(YamlLoader::json_from_str(content)?);
+    }
+    let mut documents = YamlLoader::documents_from_str(content)?;
+    if documents.len() == 1 {
+      return Ok(documents.remove(0));
+    }
+    match self {
+      YamlDocuments::Single => unreachable!("Single documents are loaded above"),
+      YamlDocuments::Array => Ok(Value::Array(documents)),
+      YamlDocuments::Merge(keys) if keys.len() != documents.len() => Err(LoaderError::YamlDocumentsKeys {
+        keys: keys.len(),
+        documents: documents.len(),
+      }),
+      YamlDocuments::Merge(keys) => Ok(Value::Object(keys.iter().cloned().zip(documents).collect::<Map<_, _>>())),
+    }
+  }
+}
+
+impl FromStr for YamlDocuments {
+  type Err = LoaderError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.split_once('=') {
+      None if s == "single" => Ok(YamlDocuments::Single),
+      None if s == "array" => Ok(YamlDocuments::Array),
+      Some(("merge", keys)) => Ok(YamlDocuments::Merge(keys.split(',').map(|k| k.trim().to_string()).collect())),
+      _ => Err(LoaderError::UnknownYamlDocuments(s.into())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use test_case::test_case;
+
+  const MANIFESTS: &str = "kind: Deployment\n---\nkind: Service\n";
+
+  #[test_case(YamlDocuments::Array, json!([{ "kind": "Deployment" }, { "kind": "Service" }]))]
+  #[test_case(YamlDocuments::Merge(vec!["deployment".into(), "service".into()]), json!({ "deployment": { "kind": "Deployment" }, "service": { "kind": "Service" } }))]
+  fn yaml_documents_load_tests(yaml_documents: YamlDocuments, expected: Value) -> Result<(), anyhow::Error> {
+    assert_eq!(yaml_documents.load(MANIFESTS)?, expected);
+    Ok(())
+  }
+
+  #[test_case(YamlDocuments::Single)]
+  #[test_case(YamlDocuments::Array)]
+  #[test_case(YamlDocuments::Merge(vec!["a".into(), "b".into()]))]
+  fn single_documents_should_be_loaded_as_is(yaml_documents: YamlDocuments) -> Result<(), anyhow::Error> {
+    assert_eq!(yaml_documents.load("kind: Service")?, json!({ "kind": "Service" }));
+    Ok(())
+  }
+
+  #[test]
+  fn merged_documents_should_all_have_a_key() {
+    let yaml_documents = YamlDocuments::Merge(vec!["deployment".into()]);
+    assert!(matches!(
+      yaml_documents.load(MANIFESTS),
+      Err(LoaderError::YamlDocumentsKeys { keys: 1, documents: 2 })
+    ));
+    assert!(YamlDocuments::Single.load(MANIFESTS).is_err());
+  }
+
+  #[test_case("single", YamlDocuments::Single)]
+  #[test_case("array", YamlDocuments::Array)]
+  #[test_case("merge=deployment, service", YamlDocuments::Merge(vec!["deployment".into(), "service".into()]))]
+  fn yaml_documents_from_str_tests(value: &str, expected: YamlDocuments) -> Result<(), anyhow::Error> {
+    assert_eq!(value.parse::<YamlDocuments>()?, expected);
+    Ok(())
+  }
+}
@@ -0,0 +1,216 @@
+use crate::loaders::{DocumentPath, LoaderError};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, path::Path, str::FromStr};
+use thiserror::Error;
+
+const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+#[derive(Error, Debug)]
+pub enum MergerError {
+  #[error("Loading error: `{0}`.")]
+  Loading(#[from] LoaderError),
+  #[error("Pattern error: `{0}`.")]
+  Pattern(#[from] glob::PatternError),
+  #[error("No source document: `source` and `sources` match no file.")]
+  NoSource,
+  #[error("Documents `{0}` and `{1}` have the same file stem, they can't be merged by file stem.")]
+  DuplicateFileStem(String, String),
+  #[error("Unknown `{0}` merge strategy, expected `deep`, `by_file_stem` or `concat`.")]
+  UnknownStrategy(String),
+}
+
+/// How several source documents are merged into one
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+  /// Objects are merged recursively, arrays are concatenated without duplicates,
+  /// and the other values are replaced by the ones of the next documents
+  #[default]
+  Deep,
+  /// Each document is set under its file name without extension
+  ByFileStem,
+  /// The items of the array documents are concatenated, the other documents being added as items
+  Concat,
+}
+
+impl FromStr for MergeStrategy {
+  type Err = MergerError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "deep" => Ok(MergeStrategy::Deep),
+      "by_file_stem" => Ok(MergeStrategy::ByFileStem),
+      "concat" => Ok(MergeStrategy::Concat),
+      _ => Err(MergerError::UnknownStrategy(s.into())),
+    }
+  }
+}
+
+/// Lists the documents of the `source` and the `sources` of a section.
+/// The `sources` glob patterns are expanded, in alphabetical order.
+#[::tracing::instrument(level = "trace")]
+pub fn source_documents(source: &str, sources: &[String]) -> Result<Vec<DocumentPath>, MergerError> {
+  let mut documents = vec![];
+  if !source.trim().is_empty() {
+    documents.push(DocumentPath::parse(source)?);
+  }
+  for pattern in sources {
+    if pattern.contains(GLOB_CHARS) {
+      let mut paths = glob::glob(pattern)?.flatten().filter(|p| p.is_file()).collect::<Vec<_>>();
+      paths.sort();
+      for path in paths {
+        documents.push(DocumentPath::FileName(path.to_string_lossy().into_owned()));
+      }
+    } else {
+      documents.push(DocumentPath::parse(pattern)?);
+    }
+  }
+  match documents.is_empty() {
+    true => Err(MergerError::NoSource),
+    false => Ok(documents),
+  }
+}
+
+/// Merges the (resolved) documents into one, in their order
+#[::tracing::instrument(level = "trace", skip(documents))]
+pub fn merge_documents(documents: Vec<(DocumentPath, Value)>, strategy: MergeStrategy) -> Result<Value, MergerError> {
+  match strategy {
+    MergeStrategy::Deep => Ok(
+      documents
+        .into_iter()
+        .fold(Value::Null, |merged, (_, document)| deep_merge(merged, document)),
+    ),
+    MergeStrategy::ByFileStem => {
+      let mut merged = Map::new();
+      let mut names = HashMap::new();
+      for (path, document) in documents {
+        let name = document_name(&path);
+        let stem = Path::new(&name)
+          .file_stem()
+          .map(|s| s.to_string_lossy().into_owned())
+          .unwrap_or_default();
+        if let Some(other) = names.insert(stem.clone(), name.clone()) {
+          return Err(MergerError::DuplicateFileStem(other, name));
+        }
+        merged.insert(stem, document);
+      }
+      Ok(Value::Object(merged))
+    }
+    MergeStrategy::Concat => {
+      let mut merged = vec![];
+      for (_, document) in documents {
+        match document {
+          Value::Array(items) => merged.extend(items),
+          document => merged.push(document),
+        }
+      }
+      Ok(Value::Array(merged))
+    }
+  }
+}
+
+fn document_name(path: &DocumentPath) -> String {
+  match path {
+    DocumentPath::Url(url) => url.path().to_string(),
+    DocumentPath::FileName(file_name) => file_name.clone(),
+    DocumentPath::None => String::new(),
+  }
+}
+
+fn deep_merge(merged: Value, next: Value) -> Value {
+  match (merged, next) {
+    (Value::Object(mut merged), Value::Object(next)) => {
+      for (key, value) in next {
+        match merged.get_mut(&key) {
+          Some(existing) => *existing = deep_merge(existing.take(), value),
+          None => {
+            merged.insert(key, value);
+          }
+        }
+      }
+      Value::Object(merged)
+    }
+    (Value::Array(mut merged), Value::Array(next)) => {
+      for item in next {
+        if !merged.contains(&item) {
+          merged.push(item);
+        }
+      }
+      Value::Array(merged)
+    }
+    (_, next) => next,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use test_case::test_case;
+
+  fn documents() -> Vec<(DocumentPath, Value)> {
+    vec![
+      (
+        DocumentPath::FileName("specs/pets.yaml".into()),
+        json!({ "info": { "title": "Pets", "version": "1" }, "schemes": ["http"], "paths": { "/pets": {} } }),
+      ),
+      (
+        DocumentPath::FileName("specs/stores.yaml".into()),
+        json!({ "info": { "title": "Stores" }, "schemes": ["http", "https"], "paths": { "/stores": {} } }),
+      ),
+    ]
+  }
+
+  #[test_case(MergeStrategy::Deep, json!({
+    "info": { "title": "Stores", "version": "1" },
+    "schemes": ["http", "https"],
+    "paths": { "/pets": {}, "/stores": {} }
+  }))]
+  #[test_case(MergeStrategy::ByFileStem, json!({
+    "pets": { "info": { "title": "Pets", "version": "1" }, "schemes": ["http"], "paths": { "/pets": {} } },
+    "stores": { "info": { "title": "Stores" }, "schemes": ["http", "https"], "paths": { "/stores": {} } }
+  }))]
+  fn merge_documents_tests(strategy: MergeStrategy, expected: Value) -> Result<(), anyhow::Error> {
+    assert_eq!(merge_documents(documents(), strategy)?, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn concat_should_flatten_array_documents() -> Result<(), anyhow::Error> {
+    let documents = vec![
+      (DocumentPath::None, json!([1, 2])),
+      (DocumentPath::None, json!([2, 3])),
+      (DocumentPath::None, json!({ "a": 4 })),
+    ];
+    assert_eq!(merge_documents(documents, MergeStrategy::Concat)?, json!([1, 2, 2, 3, { "a": 4 }]));
+    Ok(())
+  }
+
+  #[test]
+  fn by_file_stem_should_fail_on_duplicate_stems() {
+    let documents = vec![
+      (DocumentPath::FileName("v1/pets.yaml".into()), json!({})),
+      (DocumentPath::FileName("v2/pets.json".into()), json!({})),
+    ];
+    assert!(matches!(
+      merge_documents(documents, MergeStrategy::ByFileStem),
+      Err(MergerError::DuplicateFileStem(a, b)) if a == "v1/pets.yaml" && b == "v2/pets.json"
+    ));
+  }
+
+  #[test]
+  fn source_documents_should_expand_globs_in_order() -> Result<(), anyhow::Error> {
+    let documents = source_documents("", &["_samples/merger/*.json".into()])?;
+    assert_eq!(
+      documents,
+      vec![
+        DocumentPath::FileName("_samples/merger/example-minimal.json".into()),
+        DocumentPath::FileName("_samples/merger/example-minimal2.json".into()),
+        DocumentPath::FileName("_samples/merger/petstore-minimal.json".into()),
+      ]
+    );
+    assert!(matches!(source_documents("", &[]), Err(MergerError::NoSource)));
+    Ok(())
+  }
+}
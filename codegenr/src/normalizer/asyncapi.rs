@@ -0,0 +1,308 @@
+use super::{entries, or_array, or_object, synthesize_id, NormalizerError};
+use serde_json::{json, Map, Value};
+
+/// Name of the value a `$ref` targeted, added by the resolver
+const REF_NAME: &str = "x-refName";
+
+const V2_OPERATIONS: [&str; 2] = ["publish", "subscribe"];
+
+/// The action of an operation from the application point of view: `send` or `receive`.
+/// AsyncAPI 2 `publish` operations are the ones the application receives, `subscribe` ones the ones it sends.
+pub fn operation_action(key_or_action: &str) -> Option<&'static str> {
+  match key_or_action {
+    "publish" | "receive" => Some("receive"),
+    "subscribe" | "send" => Some("send"),
+    _ => None,
+  }
+}
+
+/// The messages of an AsyncAPI 2 operation (`message`, maybe a `oneOf`),
+/// or of an AsyncAPI 3 operation or channel (`messages`, an array or an object)
+pub fn operation_messages(operation: &Value) -> Vec<&Value> {
+  match (&operation["message"], &operation["messages"]) {
+    (Value::Object(message), _) => match message.get("oneOf") {
+      Some(Value::Array(messages)) => messages.iter().collect(),
+      _ => vec![&operation["message"]],
+    },
+    (_, Value::Array(messages)) => messages.iter().collect(),
+    (_, Value::Object(messages)) => messages.values().collect(),
+    _ => vec![],
+  }
+}
+
+/// The payload schema of a message, and its format.
+/// AsyncAPI 3 multi format `{ schemaFormat, schema }` payloads are unwrapped.
+pub fn message_payload(message: &Value) -> (&Value, &Value) {
+  let payload = &message["payload"];
+  match (&payload["schemaFormat"], &payload["schema"]) {
+    (format @ Value::String(_), schema) if !schema.is_null() => (schema, format),
+    _ => (payload, &message["schemaFormat"]),
+  }
+}
+
+/// Builds the `{ version, servers, channels, operations, messages }` model of an AsyncAPI 2.x or 3.x resolved document
+#[::tracing::instrument(level = "trace", skip(json))]
+pub fn normalize_asyncapi(json: &Value) -> Result<Value, NormalizerError> {
+  let version = json["asyncapi"].as_str().ok_or(NormalizerError::NotAsyncapi)?;
+  let (channels, operations) = match version.split('.').next() {
+    Some("2") => v2_channels_and_operations(json),
+    Some("3") => v3_channels_and_operations(json),
+    _ => return Err(NormalizerError::UnsupportedAsyncapiVersion(version.into())),
+  };
+
+  let mut messages = Vec::<Value>::new();
+  for message in operations
+    .iter()
+    .chain(channels.iter())
+    .flat_map(|o| o["messages"].as_array().into_iter().flatten())
+  {
+    if !messages.iter().any(|m| m["name"] == message["name"]) {
+      messages.push(message.clone());
+    }
+  }
+
+  Ok(json!({
+    "version": version,
+    "servers": servers(json),
+    "channels": channels,
+    "operations": operations,
+    "messages": messages,
+  }))
+}
+
+fn v2_channels_and_operations(json: &Value) -> (Vec<Value>, Vec<Value>) {
+  let mut channels = vec![];
+  let mut operations = vec![];
+  for (name, channel) in entries(&json["channels"]) {
+    let mut channel_messages = Vec::<Value>::new();
+    for key in V2_OPERATIONS {
+      let operation = &channel[key];
+      if !operation.is_object() {
+        continue;
+      }
+      let action = operation_action(key).unwrap_or_default();
+      let operation_id = operation["operationId"]
+        .as_str()
+        .map(Into::into)
+        .unwrap_or_else(|| synthesize_id(&[action, name]));
+      let messages = operation_messages(operation)
+        .into_iter()
+        .map(|m| normalize_message(m, None))
+        .collect::<Vec<_>>();
+      for message in messages.iter() {
+        if !channel_messages.iter().any(|m| m["name"] == message["name"]) {
+          channel_messages.push(message.clone());
+        }
+      }
+      operations.push(normalize_operation(operation, operation_id, action, name, name, messages));
+    }
+    channels.push(normalize_channel(channel, name, name, channel_messages));
+  }
+  (channels, operations)
+}
+
+fn v3_channels_and_operations(json: &Value) -> (Vec<Value>, Vec<Value>) {
+  let channels = entries(&json["channels"])
+    .map(|(name, channel)| {
+      let messages = entries(&channel["messages"])
+        .map(|(key, message)| normalize_message(message, Some(key)))
+        .collect();
+      normalize_channel(channel, name, channel["address"].as_str().unwrap_or_default(), messages)
+    })
+    .collect::<Vec<_>>();
+
+  let mut operations = vec![];
+  for (operation_id, operation) in entries(&json["operations"]) {
+    let action = operation["action"].as_str().and_then(operation_action).unwrap_or_default();
+    let channel = &operation["channel"];
+    let messages = match operation["messages"] {
+      Value::Array(_) => operation_messages(operation)
+        .into_iter()
+        .map(|m| normalize_message(m, None))
+        .collect(),
+      _ => entries(&channel["messages"])
+        .map(|(key, m)| normalize_message(m, Some(key)))
+        .collect(),
+    };
+    operations.push(normalize_operation(
+      operation,
+      operation_id.into(),
+      action,
+      channel[REF_NAME].as_str().unwrap_or_default(),
+      channel["address"].as_str().unwrap_or_default(),
+      messages,
+    ));
+  }
+  (channels, operations)
+}
+
+fn normalize_channel(channel: &Value, name: &str, address: &str, messages: Vec<Value>) -> Value {
+  let parameters = entries(&channel["parameters"])
+    .map(|(name, parameter)| {
+      let mut normalized = Map::new();
+      normalized.insert("name".into(), name.into());
+      normalized.extend(parameter.as_object().into_iter().flatten().map(|(k, v)| (k.clone(), v.clone())));
+      Value::Object(normalized)
+    })
+    .collect::<Vec<_>>();
+  json!({
+    "name": name,
+    "address": address,
+    "description": channel["description"],
+    "parameters": parameters,
+    "bindings": or_object(&channel["bindings"]),
+    "messages": messages,
+  })
+}
+
+fn normalize_operation(operation: &Value, operation_id: String, action: &str, channel: &str, address: &str, messages: Vec<Value>) -> Value {
+  json!({
+    "operationId": operation_id,
+    "action": action,
+    "channel": channel,
+    "address": address,
+    "summary": operation["summary"],
+    "description": operation["description"],
+    "tags": or_array(&operation["tags"]),
+    "security": or_array(&operation["security"]),
+    "bindings": or_object(&operation["bindings"]),
+    "messages": messages,
+  })
+}
+
+/// The name of a message is its `name`, its `messageId`, its key in the channel, the name of the `$ref` to it, or its `title`
+fn normalize_message(message: &Value, key: Option<&str>) -> Value {
+  let name = message["name"]
+    .as_str()
+    .or_else(|| message["messageId"].as_str())
+    .or(key)
+    .or_else(|| message[REF_NAME].as_str())
+    .or_else(|| message["title"].as_str());
+  let (payload, schema_format) = message_payload(message);
+  json!({
+    "name": name,
+    "title": message["title"],
+    "summary": message["summary"],
+    "description": message["description"],
+    "contentType": message["contentType"],
+    "headers": message["headers"],
+    "correlationId": message["correlationId"],
+    "payload": payload,
+    "schemaFormat": schema_format,
+    "tags": or_array(&message["tags"]),
+    "bindings": or_object(&message["bindings"]),
+  })
+}
+
+/// The servers with both their AsyncAPI 2 `url` and their AsyncAPI 3 `host` and `pathname`
+fn servers(json: &Value) -> Vec<Value> {
+  entries(&json["servers"])
+    .map(|(name, server)| {
+      let protocol = server["protocol"].as_str().unwrap_or_default();
+      let (url, host, pathname) = match server["url"].as_str() {
+        Some(url) => {
+          let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+          let (host, pathname) = match without_scheme.find('/') {
+            Some(index) => without_scheme.split_at(index),
+            None => (without_scheme, ""),
+          };
+          (url.to_string(), host.to_string(), pathname.to_string())
+        }
+        None => {
+          let host = server["host"].as_str().unwrap_or_default();
+          let pathname = server["pathname"].as_str().unwrap_or_default();
+          let url = match protocol.is_empty() {
+            true => format!("{host}{pathname}"),
+            false => format!("{protocol}://{host}{pathname}"),
+          };
+          (url, host.into(), pathname.into())
+        }
+      };
+      json!({
+        "name": name,
+        "url": url,
+        "host": host,
+        "pathname": pathname,
+        "protocol": protocol,
+        "protocolVersion": server["protocolVersion"],
+        "description": server["description"],
+        "variables": or_object(&server["variables"]),
+        "bindings": or_object(&server["bindings"]),
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::resolver::resolve_sample;
+  use test_case::test_case;
+
+  fn normalized(path: &str) -> Result<Value, anyhow::Error> {
+    Ok(normalize_asyncapi(&*resolve_sample(path)?)?)
+  }
+
+  fn names(values: &Value, key: &str) -> Vec<Value> {
+    values.as_array().into_iter().flatten().map(|v| v[key].clone()).collect()
+  }
+
+  #[test_case(
+    "_samples/asyncapi/streetlights.v2.yaml",
+    "mqtt://broker.example.com:1883",
+    "smartylighting/streetlights/{streetlightId}/lighting/measured"
+  )]
+  #[test_case(
+    "_samples/asyncapi/streetlights.v3.yaml",
+    "mqtt://broker.example.com:1883/mqtt",
+    "lightingMeasured"
+  )]
+  fn asyncapi_documents_should_be_normalized(path: &str, url: &str, channel: &str) -> Result<(), anyhow::Error> {
+    let model = normalized(path)?;
+    assert_eq!(model["servers"][0]["url"], url);
+    assert_eq!(model["servers"][0]["host"], "broker.example.com:1883");
+
+    assert_eq!(model["channels"][0]["name"], channel);
+    assert_eq!(
+      model["channels"][0]["address"],
+      "smartylighting/streetlights/{streetlightId}/lighting/measured"
+    );
+    assert_eq!(names(&model["channels"][0]["parameters"], "name"), vec![json!("streetlightId")]);
+
+    let operations = &model["operations"];
+    assert_eq!(names(operations, "action"), vec![json!("receive"), json!("send")]);
+    assert_eq!(operations[0]["operationId"], "receiveLightMeasurement");
+    assert_eq!(operations[0]["tags"], json!([{ "name": "lighting" }]));
+    assert_eq!(operations[1]["channel"], model["channels"][1]["name"]);
+    assert_eq!(operations[1]["bindings"], json!({ "mqtt": { "qos": 1 } }));
+
+    let message = &operations[0]["messages"][0];
+    assert_eq!(message["name"], "lightMeasured");
+    assert_eq!(message["contentType"], "application/json");
+    assert_eq!(message["payload"]["properties"]["lumens"]["type"], "integer");
+    assert_eq!(
+      names(&model["messages"], "name"),
+      vec![json!("lightMeasured"), json!("turnOn"), json!("turnOff")]
+    );
+    assert_eq!(model["messages"][1]["payload"]["properties"]["command"]["type"], "string");
+    Ok(())
+  }
+
+  #[test]
+  fn v2_operation_ids_should_be_synthesized() -> Result<(), anyhow::Error> {
+    let model = normalized("_samples/asyncapi/streetlights.v2.yaml")?;
+    assert_eq!(
+      model["operations"][1]["operationId"],
+      "sendSmartylightingStreetlightsStreetlightIdTurn"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn unsupported_versions_should_fail() {
+    assert!(matches!(
+      normalize_asyncapi(&json!({ "asyncapi": "1.2.0" })),
+      Err(NormalizerError::UnsupportedAsyncapiVersion(v)) if v == "1.2.0"
+    ));
+  }
+}
@@ -0,0 +1,186 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{rc::Rc, str::FromStr};
+use thiserror::Error;
+
+pub mod asyncapi;
+pub mod openapi3;
+pub mod operations;
+
+/// Key of the root object under which the normalized models are added
+pub const CODEGENR_EXTENSION: &str = "x-codegenr";
+/// Keys of the operations of an OpenAPI path item
+pub(crate) const HTTP_METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+#[derive(Error, Debug)]
+pub enum NormalizerError {
+  #[error("Unknown `{0}` normalization, expected `asyncapi`, `openapi3`, `operations` or `tags`.")]
+  UnknownNormalization(String),
+  #[error("Unknown `{0}` operations grouping, expected `tag`, `path`, `path:<skipped segments>` or an `x-` extension.")]
+  UnknownGrouping(String),
+  #[error("The `{0}` normalization needs an object document.")]
+  NotAnObject(&'static str),
+  #[error("The document is not an AsyncAPI one: it has no `asyncapi` version.")]
+  NotAsyncapi,
+  #[error("The document is neither a Swagger 2.0 nor an OpenAPI 3 one.")]
+  NotOpenapi,
+  #[error("The document is not an OpenAPI 3 one, a Swagger 2.0 one needs the `openapi3` normalization first.")]
+  NotOpenapi3,
+  #[error("The `{0}` operationId is declared by several operations.")]
+  DuplicateOperationId(String),
+  #[error("AsyncAPI `{0}` documents are not supported, expected a 2.x or 3.x one.")]
+  UnsupportedAsyncapiVersion(String),
+}
+
+/// A transformation of the resolved document, done before rendering it.
+/// It is written like on the command line, eg: `tags=x-controller`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Normalization {
+  /// Adds the channels, operations, messages and servers of an AsyncAPI 2.x or 3.x document under `x-codegenr.asyncapi`
+  Asyncapi,
+  /// Converts a Swagger 2.0 document to the OpenAPI 3 layout, keeping OpenAPI 3 documents as they are
+  Openapi3,
+  /// Adds the flat list of the operations of an OpenAPI 3 document under `x-codegenr.operations`
+  Operations,
+  /// Adds the OpenAPI 3 operations grouped by tag under `x-codegenr.tags`
+  Tags(operations::GroupOperationsBy),
+}
+
+impl FromStr for Normalization {
+  type Err = NormalizerError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.split_once('=') {
+      None if s == "asyncapi" => Ok(Normalization::Asyncapi),
+      None if s == "openapi3" => Ok(Normalization::Openapi3),
+      None if s == "operations" => Ok(Normalization::Operations),
+      None if s == "tags" => Ok(Normalization::Tags(Default::default())),
+      Some(("tags", group_by)) => Ok(Normalization::Tags(group_by.parse()?)),
+      _ => Err(NormalizerError::UnknownNormalization(s.into())),
+    }
+  }
+}
+
+impl TryFrom<String> for Normalization {
+  type Error = NormalizerError;
+
+  fn try_from(s: String) -> Result<Self, Self::Error> {
+    s.parse()
+  }
+}
+
+/// Applies the normalizations to the resolved document, in their order
+#[::tracing::instrument(level = "trace", skip(json))]
+pub fn normalize(json: Rc<Value>, normalizations: &[Normalization]) -> Result<Rc<Value>, NormalizerError> {
+  if normalizations.is_empty() {
+    return Ok(json);
+  }
+  let mut json = (*json).clone();
+  for normalization in normalizations {
+    match normalization {
+      Normalization::Asyncapi => {
+        let model = asyncapi::normalize_asyncapi(&json)?;
+        codegenr_extension(&mut json, "asyncapi")?.insert("asyncapi".into(), model);
+      }
+      Normalization::Openapi3 => json = openapi3::swagger2_to_openapi3(&json)?,
+      Normalization::Operations => {
+        let operations = operations::openapi3_operations(&json)?;
+        codegenr_extension(&mut json, "operations")?.insert("operations".into(), Value::Array(operations));
+      }
+      Normalization::Tags(group_by) => {
+        let operations = operations::openapi3_operations(&json)?;
+        let tags = operations::group_operations(&json, operations, group_by);
+        codegenr_extension(&mut json, "tags")?.insert("tags".into(), Value::Array(tags));
+      }
+    }
+  }
+  Ok(Rc::new(json))
+}
+
+/// The `x-codegenr` object of the document, created if needed
+fn codegenr_extension<'a>(json: &'a mut Value, normalization: &'static str) -> Result<&'a mut Map<String, Value>, NormalizerError> {
+  let root = json.as_object_mut().ok_or(NormalizerError::NotAnObject(normalization))?;
+  let extension = root.entry(CODEGENR_EXTENSION).or_insert_with(|| Value::Object(Default::default()));
+  if !extension.is_object() {
+    *extension = Value::Object(Default::default());
+  }
+  extension.as_object_mut().ok_or(NormalizerError::NotAnObject(normalization))
+}
+
+/// Builds a camelCase identifier from the words of all the parts, eg: `receive` and `user/{id}/signed-up` give `receiveUserIdSignedUp`
+pub(crate) fn synthesize_id(parts: &[&str]) -> String {
+  let mut id = String::new();
+  for word in parts
+    .iter()
+    .flat_map(|p| p.split(|c: char| !c.is_ascii_alphanumeric()))
+    .filter(|w| !w.is_empty())
+  {
+    let mut chars = word.chars();
+    if let Some(first) = chars.next() {
+      match id.is_empty() {
+        true => id.extend(first.to_lowercase()),
+        false => id.extend(first.to_uppercase()),
+      }
+      id.push_str(chars.as_str());
+    }
+  }
+  id
+}
+
+/// The keys and values of an object, none if the value is not one
+pub(crate) fn entries(value: &Value) -> impl Iterator<Item = (&str, &Value)> {
+  value.as_object().into_iter().flatten().map(|(k, v)| (k.as_str(), v))
+}
+
+/// The value if it is an array, an empty one otherwise
+pub(crate) fn or_array(value: &Value) -> Value {
+  match value {
+    Value::Array(_) => value.clone(),
+    _ => Value::Array(vec![]),
+  }
+}
+
+/// The value if it is an object, an empty one otherwise
+pub(crate) fn or_object(value: &Value) -> Value {
+  match value {
+    Value::Object(_) => value.clone(),
+    _ => Value::Object(Default::default()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use test_case::test_case;
+
+  #[test_case(&["receive", "user/{id}/signed-up"], "receiveUserIdSignedUp")]
+  #[test_case(&["Get", "/pets"], "getPets")]
+  #[test_case(&[], "")]
+  fn synthesize_id_tests(parts: &[&str], expected: &str) {
+    assert_eq!(synthesize_id(parts), expected);
+  }
+
+  #[test_case("asyncapi", Normalization::Asyncapi)]
+  #[test_case("tags", Normalization::Tags(operations::GroupOperationsBy::FirstTag))]
+  #[test_case("tags=path", Normalization::Tags(operations::GroupOperationsBy::PathPrefix(0)))]
+  #[test_case("tags=path:1", Normalization::Tags(operations::GroupOperationsBy::PathPrefix(1)))]
+  #[test_case("tags=x-controller", Normalization::Tags(operations::GroupOperationsBy::Extension("x-controller".into())))]
+  fn normalization_from_str_tests(value: &str, expected: Normalization) -> Result<(), anyhow::Error> {
+    assert_eq!(value.parse::<Normalization>()?, expected);
+    assert_eq!(serde_json::from_value::<Normalization>(json!(value))?, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn normalize_should_keep_the_document_without_normalizations() -> Result<(), anyhow::Error> {
+    let json = Rc::new(json!({ "asyncapi": "2.6.0" }));
+    assert!(Rc::ptr_eq(&normalize(json.clone(), &[])?, &json));
+    assert!(matches!(
+      normalize(Rc::new(json!([])), &[Normalization::Asyncapi]),
+      Err(NormalizerError::NotAsyncapi)
+    ));
+    Ok(())
+  }
+}
@@ -0,0 +1,574 @@
+use super::{entries, NormalizerError, HTTP_METHODS};
+use serde_json::{json, Map, Value};
+
+const OPENAPI_VERSION: &str = "3.0.3";
+const DEFAULT_MEDIA_TYPE: &str = "application/json";
+const DEFAULT_SCHEME: &str = "https";
+const FORM_MEDIA_TYPES: [&str; 2] = ["application/x-www-form-urlencoded", "multipart/form-data"];
+/// Keys of a Swagger 2.0 non body parameter, header or items describing its value, moved to its OpenAPI 3 `schema`
+const SCHEMA_KEYS: [&str; 16] = [
+  "type",
+  "format",
+  "items",
+  "default",
+  "maximum",
+  "exclusiveMaximum",
+  "minimum",
+  "exclusiveMinimum",
+  "maxLength",
+  "minLength",
+  "pattern",
+  "maxItems",
+  "minItems",
+  "uniqueItems",
+  "enum",
+  "multipleOf",
+];
+/// Keys whose values are markers of the inlined `$ref`s, rewritten like the `$ref`s themselves
+const REF_KEYS: [&str; 2] = ["$ref", "x-fromRef"];
+
+/// Converts a resolved Swagger 2.0 document to the OpenAPI 3 layout. OpenAPI 3 documents are kept as is.
+#[::tracing::instrument(level = "trace", skip(json))]
+pub fn swagger2_to_openapi3(json: &Value) -> Result<Value, NormalizerError> {
+  if json["openapi"].as_str().is_some_and(|v| v.starts_with('3')) {
+    return Ok(json.clone());
+  }
+  let root = match (json["swagger"].as_str(), json.as_object()) {
+    (Some("2.0"), Some(root)) => root,
+    _ => return Err(NormalizerError::NotOpenapi),
+  };
+  let consumes = media_types(&json["consumes"], &[]);
+  let produces = media_types(&json["produces"], &[]);
+
+  let mut converted = Map::new();
+  converted.insert("openapi".into(), OPENAPI_VERSION.into());
+  let mut components = Map::new();
+  let mut request_bodies = Map::new();
+  for (key, value) in root {
+    match key.as_str() {
+      "swagger" | "host" | "basePath" | "schemes" | "consumes" | "produces" => {}
+      "info" => {
+        converted.insert(key.clone(), value.clone());
+        converted.insert("servers".into(), servers(json));
+      }
+      "paths" => {
+        let paths = entries(value)
+          .map(|(path, item)| (path.to_string(), convert_path_item(item, &consumes, &produces)))
+          .collect();
+        converted.insert(key.clone(), Value::Object(paths));
+      }
+      "definitions" => {
+        let schemas = entries(value).map(|(name, s)| (name.to_string(), convert_schema(s))).collect();
+        components.insert("schemas".into(), Value::Object(schemas));
+      }
+      "parameters" => {
+        let mut parameters = Map::new();
+        for (name, parameter) in entries(value) {
+          match parameter["in"].as_str() {
+            Some("body" | "formData") => {
+              if let Some(request_body) = request_body(&[parameter], &consumes) {
+                request_bodies.insert(name.to_string(), request_body);
+              }
+            }
+            _ => {
+              parameters.insert(name.to_string(), convert_parameter(parameter));
+            }
+          }
+        }
+        components.insert("parameters".into(), Value::Object(parameters));
+      }
+      "responses" => {
+        let responses = entries(value)
+          .map(|(name, r)| (name.to_string(), convert_response(r, &produces)))
+          .collect();
+        components.insert("responses".into(), Value::Object(responses));
+      }
+      "securityDefinitions" => {
+        let schemes = entries(value)
+          .map(|(name, s)| (name.to_string(), convert_security_scheme(s)))
+          .collect();
+        components.insert("securitySchemes".into(), Value::Object(schemes));
+      }
+      _ => {
+        converted.insert(key.clone(), value.clone());
+      }
+    }
+  }
+  if !converted.contains_key("servers") {
+    converted.insert("servers".into(), servers(json));
+  }
+  if !request_bodies.is_empty() {
+    components.insert("requestBodies".into(), Value::Object(request_bodies.clone()));
+  }
+  if !components.is_empty() {
+    converted.insert("components".into(), Value::Object(components));
+  }
+
+  let mut converted = Value::Object(converted);
+  rewrite_refs(&mut converted, &request_bodies);
+  Ok(converted)
+}
+
+/// One server for each of the `schemes`, from the `host` and `basePath`
+fn servers(json: &Value) -> Value {
+  let base_path = json["basePath"].as_str().unwrap_or_default();
+  let servers = match json["host"].as_str() {
+    Some(host) => media_types(&json["schemes"], &[DEFAULT_SCHEME.into()])
+      .iter()
+      .map(|scheme| json!({ "url": format!("{scheme}://{host}{base_path}") }))
+      .collect(),
+    None => vec![json!({ "url": if base_path.is_empty() { "/" } else { base_path } })],
+  };
+  Value::Array(servers)
+}
+
+fn convert_path_item(item: &Value, consumes: &[String], produces: &[String]) -> Value {
+  let path_parameters = parameters(item);
+  let mut converted = Map::new();
+  for (key, value) in entries(item) {
+    match key {
+      "parameters" => {
+        let parameters = non_body_parameters(&path_parameters);
+        if !parameters.is_empty() {
+          converted.insert(key.to_string(), Value::Array(parameters));
+        }
+      }
+      method if HTTP_METHODS.contains(&method) => {
+        converted.insert(key.to_string(), convert_operation(value, &path_parameters, consumes, produces));
+      }
+      _ => {
+        converted.insert(key.to_string(), value.clone());
+      }
+    }
+  }
+  Value::Object(converted)
+}
+
+/// The `in: body` and `in: formData` parameters of the operation (and of its path) become its `requestBody`
+fn convert_operation(operation: &Value, path_parameters: &[&Value], consumes: &[String], produces: &[String]) -> Value {
+  let consumes = media_types(&operation["consumes"], consumes);
+  let produces = media_types(&operation["produces"], produces);
+  let parameters = parameters(operation);
+  let mut body_parameters = parameters.iter().copied().filter(|p| is_body(p)).collect::<Vec<_>>();
+  for path_parameter in path_parameters.iter().copied().filter(|p| is_body(p)) {
+    let overridden = parameters
+      .iter()
+      .any(|p| p["name"] == path_parameter["name"] && p["in"] == path_parameter["in"]);
+    if !overridden {
+      body_parameters.push(path_parameter);
+    }
+  }
+  let mut request_body = request_body(&body_parameters, &consumes);
+
+  let mut converted = Map::new();
+  for (key, value) in entries(operation) {
+    match key {
+      "consumes" | "produces" | "schemes" => {}
+      "parameters" => {
+        let parameters = non_body_parameters(&parameters);
+        if !parameters.is_empty() {
+          converted.insert(key.to_string(), Value::Array(parameters));
+        }
+        if let Some(request_body) = request_body.take() {
+          converted.insert("requestBody".into(), request_body);
+        }
+      }
+      "responses" => {
+        let responses = entries(value)
+          .map(|(status, r)| (status.to_string(), convert_response(r, &produces)))
+          .collect();
+        converted.insert(key.to_string(), Value::Object(responses));
+      }
+      _ => {
+        converted.insert(key.to_string(), value.clone());
+      }
+    }
+  }
+  if let Some(request_body) = request_body {
+    converted.insert("requestBody".into(), request_body);
+  }
+  Value::Object(converted)
+}
+
+fn parameters(value: &Value) -> Vec<&Value> {
+  value["parameters"].as_array().into_iter().flatten().collect()
+}
+
+fn is_body(parameter: &Value) -> bool {
+  matches!(parameter["in"].as_str(), Some("body" | "formData"))
+}
+
+fn non_body_parameters(parameters: &[&Value]) -> Vec<Value> {
+  parameters.iter().filter(|p| !is_body(p)).map(|p| convert_parameter(p)).collect()
+}
+
+/// The value describing keys move to the `schema`, and the `collectionFormat` becomes a `style`
+fn convert_parameter(parameter: &Value) -> Value {
+  let mut converted = Map::new();
+  for (key, value) in entries(parameter) {
+    if !SCHEMA_KEYS.contains(&key) && key != "collectionFormat" {
+      converted.insert(key.to_string(), value.clone());
+    }
+  }
+  if parameter["type"].is_string() {
+    converted.insert("schema".into(), value_schema(parameter));
+  }
+  if parameter["type"] == "array" {
+    let (style, explode) = match (parameter["collectionFormat"].as_str().unwrap_or("csv"), parameter["in"].as_str()) {
+      ("csv", Some("query" | "cookie")) => (Some("form"), false),
+      ("multi", _) => (Some("form"), true),
+      ("ssv", _) => (Some("spaceDelimited"), false),
+      ("pipes", _) => (Some("pipeDelimited"), false),
+      _ => (None, false),
+    };
+    if let Some(style) = style {
+      converted.insert("style".into(), style.into());
+      converted.insert("explode".into(), explode.into());
+    }
+  }
+  Value::Object(converted)
+}
+
+/// The schema of a non body parameter, a header or their `items`
+fn value_schema(value: &Value) -> Value {
+  let mut schema = Map::new();
+  for (key, value) in entries(value) {
+    match key {
+      "items" => {
+        schema.insert(key.to_string(), value_schema(value));
+      }
+      "type" if value == "file" => {
+        schema.insert("type".into(), "string".into());
+        schema.insert("format".into(), "binary".into());
+      }
+      key if SCHEMA_KEYS.contains(&key) => {
+        schema.insert(key.into(), value.clone());
+      }
+      _ => {}
+    }
+  }
+  Value::Object(schema)
+}
+
+/// A `body` parameter schema, or an object schema of the `formData` parameters, for each media type.
+/// The body parameter name is kept as `x-bodyName`.
+fn request_body(parameters: &[&Value], consumes: &[String]) -> Option<Value> {
+  let mut request_body = Map::new();
+  let (schema, media_types) = match parameters.iter().find(|p| p["in"] == "body") {
+    Some(body) => {
+      copy_keys(body, &mut request_body, &["description", "required"]);
+      request_body.insert("x-bodyName".into(), body["name"].clone());
+      let media_types = match consumes.is_empty() {
+        true => vec![DEFAULT_MEDIA_TYPE.into()],
+        false => consumes.to_vec(),
+      };
+      (convert_schema(&body["schema"]), media_types)
+    }
+    None if parameters.is_empty() => return None,
+    None => {
+      let mut properties = Map::new();
+      let mut required = vec![];
+      for parameter in parameters {
+        let name = parameter["name"].as_str().unwrap_or_default();
+        let mut schema = value_schema(parameter);
+        if let (Some(schema), Some(description)) = (schema.as_object_mut(), parameter.get("description")) {
+          schema.insert("description".into(), description.clone());
+        }
+        properties.insert(name.into(), schema);
+        if parameter["required"] == true {
+          required.push(Value::from(name));
+        }
+      }
+      let mut schema = json!({ "type": "object", "properties": properties });
+      if !required.is_empty() {
+        schema["required"] = Value::Array(required);
+        request_body.insert("required".into(), true.into());
+      }
+      let mut media_types = consumes
+        .iter()
+        .filter(|c| FORM_MEDIA_TYPES.contains(&c.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+      if media_types.is_empty() {
+        let has_file = parameters.iter().any(|p| p["type"] == "file");
+        media_types.push(FORM_MEDIA_TYPES[usize::from(has_file)].into());
+      }
+      (schema, media_types)
+    }
+  };
+  let content = media_types.into_iter().map(|m| (m, json!({ "schema": schema }))).collect();
+  request_body.insert("content".into(), Value::Object(content));
+  if let [parameter] = parameters {
+    copy_extensions(parameter, &mut request_body);
+  }
+  Some(Value::Object(request_body))
+}
+
+/// The `schema` and `examples` of a response go to its `content`, for each media type
+fn convert_response(response: &Value, produces: &[String]) -> Value {
+  let mut converted = Map::new();
+  for (key, value) in entries(response) {
+    match key {
+      "schema" | "examples" => {}
+      "headers" => {
+        let headers = entries(value)
+          .map(|(name, header)| {
+            let mut converted = Map::new();
+            copy_keys(header, &mut converted, &["description"]);
+            converted.insert("schema".into(), value_schema(header));
+            copy_extensions(header, &mut converted);
+            (name.to_string(), Value::Object(converted))
+          })
+          .collect();
+        converted.insert(key.to_string(), Value::Object(headers));
+      }
+      _ => {
+        converted.insert(key.to_string(), value.clone());
+      }
+    }
+  }
+  if !response["schema"].is_null() {
+    let schema = convert_schema(&response["schema"]);
+    let media_types = match produces.is_empty() {
+      true => vec![DEFAULT_MEDIA_TYPE.into()],
+      false => produces.to_vec(),
+    };
+    let content = media_types
+      .into_iter()
+      .map(|media_type| {
+        let mut content = json!({ "schema": schema });
+        if let Some(example) = response["examples"].get(&media_type) {
+          content["example"] = example.clone();
+        }
+        (media_type, content)
+      })
+      .collect();
+    converted.insert("content".into(), Value::Object(content));
+  }
+  Value::Object(converted)
+}
+
+/// `x-nullable` becomes `nullable`, a `discriminator` property name a `discriminator` object, and `file` a binary string
+fn convert_schema(schema: &Value) -> Value {
+  let Value::Object(schema) = schema else {
+    return schema.clone();
+  };
+  let mut converted = Map::new();
+  for (key, value) in schema {
+    match (key.as_str(), value) {
+      ("x-nullable", _) => {
+        converted.insert("nullable".into(), value.clone());
+      }
+      ("discriminator", Value::String(property)) => {
+        converted.insert(key.clone(), json!({ "propertyName": property }));
+      }
+      ("type", Value::String(t)) if t == "file" => {
+        converted.insert(key.clone(), "string".into());
+        converted.insert("format".into(), "binary".into());
+      }
+      ("properties", Value::Object(properties)) => {
+        let properties = properties.iter().map(|(k, v)| (k.clone(), convert_schema(v))).collect();
+        converted.insert(key.clone(), Value::Object(properties));
+      }
+      ("items" | "additionalProperties" | "not", _) => {
+        converted.insert(key.clone(), convert_schema(value));
+      }
+      ("allOf" | "anyOf" | "oneOf", Value::Array(schemas)) => {
+        converted.insert(key.clone(), Value::Array(schemas.iter().map(convert_schema).collect()));
+      }
+      _ => {
+        converted.insert(key.clone(), value.clone());
+      }
+    }
+  }
+  Value::Object(converted)
+}
+
+/// `basic` becomes an `http` scheme, and the `oauth2` flow a `flows` object
+fn convert_security_scheme(scheme: &Value) -> Value {
+  let mut converted = Map::new();
+  match scheme["type"].as_str() {
+    Some("basic") => {
+      converted.insert("type".into(), "http".into());
+      converted.insert("scheme".into(), "basic".into());
+    }
+    Some("oauth2") => {
+      converted.insert("type".into(), "oauth2".into());
+      let (flow, urls): (_, &[&str]) = match scheme["flow"].as_str().unwrap_or_default() {
+        "implicit" => ("implicit", &["authorizationUrl"]),
+        "password" => ("password", &["tokenUrl"]),
+        "application" => ("clientCredentials", &["tokenUrl"]),
+        _ => ("authorizationCode", &["authorizationUrl", "tokenUrl"]),
+      };
+      let mut flow_object = Map::new();
+      copy_keys(scheme, &mut flow_object, urls);
+      flow_object.insert("scopes".into(), scheme.get("scopes").cloned().unwrap_or_else(|| json!({})));
+      converted.insert("flows".into(), json!({ flow: flow_object }));
+    }
+    _ => copy_keys(scheme, &mut converted, &["type", "name", "in"]),
+  }
+  copy_keys(scheme, &mut converted, &["description"]);
+  copy_extensions(scheme, &mut converted);
+  Value::Object(converted)
+}
+
+/// Points the local `$ref`s (and the resolver markers of the inlined ones) to their OpenAPI 3 `components`
+fn rewrite_refs(value: &mut Value, request_bodies: &Map<String, Value>) {
+  match value {
+    Value::Object(map) => {
+      for (key, value) in map.iter_mut() {
+        match value {
+          Value::String(reference) if REF_KEYS.contains(&key.as_str()) => {
+            if let Some(rewritten) = rewrite_ref(reference, request_bodies) {
+              *reference = rewritten;
+            }
+          }
+          value => rewrite_refs(value, request_bodies),
+        }
+      }
+    }
+    Value::Array(values) => values.iter_mut().for_each(|v| rewrite_refs(v, request_bodies)),
+    _ => {}
+  }
+}
+
+fn rewrite_ref(reference: &str, request_bodies: &Map<String, Value>) -> Option<String> {
+  let (section, rest) = reference.strip_prefix("#/")?.split_once('/')?;
+  let name = rest.split('/').next().unwrap_or_default();
+  let component = match section {
+    "definitions" => "schemas",
+    "parameters" if request_bodies.contains_key(name) => "requestBodies",
+    "parameters" => "parameters",
+    "responses" => "responses",
+    "securityDefinitions" => "securitySchemes",
+    _ => return None,
+  };
+  Some(format!("#/components/{component}/{rest}"))
+}
+
+fn media_types(value: &Value, default: &[String]) -> Vec<String> {
+  match value.as_array() {
+    Some(values) => values.iter().filter_map(|v| v.as_str().map(Into::into)).collect(),
+    None => default.to_vec(),
+  }
+}
+
+fn copy_keys(from: &Value, to: &mut Map<String, Value>, keys: &[&str]) {
+  for key in keys {
+    if let Some(value) = from.get(key) {
+      to.insert((*key).into(), value.clone());
+    }
+  }
+}
+
+fn copy_extensions(from: &Value, to: &mut Map<String, Value>) {
+  for (key, value) in entries(from).filter(|(k, _)| k.starts_with("x-")) {
+    to.insert(key.to_string(), value.clone());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::resolver::resolve_sample;
+
+  fn converted() -> Result<Value, anyhow::Error> {
+    Ok(swagger2_to_openapi3(&*resolve_sample("_samples/swagger2/petstore.yaml")?)?)
+  }
+
+  #[test]
+  fn swagger2_root_should_be_converted() -> Result<(), anyhow::Error> {
+    let json = converted()?;
+    let keys = json.as_object().map(|o| o.keys().cloned().collect::<Vec<_>>());
+    assert_eq!(
+      keys,
+      Some(vec![
+        "openapi".into(),
+        "info".into(),
+        "servers".into(),
+        "tags".into(),
+        "paths".into(),
+        "components".into()
+      ])
+    );
+    assert_eq!(
+      json["servers"],
+      json!([{ "url": "https://petstore.example.com/v1" }, { "url": "http://petstore.example.com/v1" }])
+    );
+    let components = &json["components"];
+    assert_eq!(components["schemas"]["Pet"]["discriminator"], json!({ "propertyName": "kind" }));
+    assert_eq!(components["schemas"]["Pet"]["properties"]["nickname"]["nullable"], true);
+    assert_eq!(
+      components["parameters"]["limit"],
+      json!({ "name": "limit", "in": "query", "schema": { "type": "integer", "default": 20 } })
+    );
+    assert_eq!(
+      components["responses"]["Created"]["content"]["application/json"]["schema"]["type"],
+      "object"
+    );
+    assert_eq!(components["securitySchemes"]["basic"], json!({ "type": "http", "scheme": "basic" }));
+    assert_eq!(
+      components["securitySchemes"]["petstore_auth"]["flows"]["authorizationCode"]["tokenUrl"],
+      "https://petstore.example.com/oauth/token"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn swagger2_operations_should_be_converted() -> Result<(), anyhow::Error> {
+    let json = converted()?;
+    let list = &json["paths"]["/pets"]["get"];
+    assert_eq!(
+      list["parameters"][0],
+      json!({ "name": "tags", "in": "query", "schema": { "type": "array", "items": { "type": "string" } }, "style": "form", "explode": false })
+    );
+    assert_eq!(list["parameters"][1]["x-fromRef"], "#/components/parameters/limit");
+    let ok = &list["responses"]["200"];
+    assert_eq!(ok["headers"]["X-Total"], json!({ "schema": { "type": "integer" } }));
+    assert_eq!(
+      ok["content"]["application/json"]["schema"]["items"]["x-fromRef"],
+      "#/components/schemas/Pet"
+    );
+
+    let create = &json["paths"]["/pets"]["post"];
+    assert_eq!(create["requestBody"]["x-bodyName"], "pet");
+    assert_eq!(create["requestBody"]["required"], true);
+    assert_eq!(
+      create["requestBody"]["content"]["application/json"]["schema"]["required"],
+      json!(["name"])
+    );
+    assert_eq!(create["responses"]["201"]["x-fromRef"], "#/components/responses/Created");
+
+    let upload = &json["paths"]["/pets/{petId}/photo"];
+    assert_eq!(
+      upload["parameters"],
+      json!([{ "name": "petId", "in": "path", "required": true, "schema": { "type": "integer", "format": "int64" } }])
+    );
+    assert_eq!(
+      upload["put"]["requestBody"],
+      json!({
+        "required": true,
+        "content": {
+          "multipart/form-data": {
+            "schema": {
+              "type": "object",
+              "properties": { "file": { "type": "string", "format": "binary" }, "caption": { "type": "string" } },
+              "required": ["file"]
+            }
+          }
+        }
+      })
+    );
+    assert_eq!(upload["put"].get("consumes"), None);
+    assert_eq!(upload["put"]["security"], json!([{ "petstore_auth": ["write:pets"] }]));
+    Ok(())
+  }
+
+  #[test]
+  fn openapi3_documents_should_be_kept() -> Result<(), anyhow::Error> {
+    let json = json!({ "openapi": "3.0.0", "paths": {} });
+    assert_eq!(swagger2_to_openapi3(&json)?, json);
+    assert!(matches!(swagger2_to_openapi3(&json!({})), Err(NormalizerError::NotOpenapi)));
+    Ok(())
+  }
+}
@@ -0,0 +1,414 @@
+use super::{entries, or_array, synthesize_id, NormalizerError, HTTP_METHODS};
+use serde_json::{json, Map, Value};
+use std::{
+  collections::{HashMap, HashSet},
+  str::FromStr,
+};
+
+const PARAMETER_LOCATIONS: [&str; 4] = ["path", "query", "header", "cookie"];
+const DEFAULT_RESPONSE: &str = "default";
+/// Group of the operations having no tag
+const DEFAULT_TAG: &str = "default";
+
+/// How the operations are grouped
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub enum GroupOperationsBy {
+  /// By their first tag
+  #[default]
+  FirstTag,
+  /// By the value of an `x-` extension of the operations, or their first tag when they don't have it
+  Extension(String),
+  /// By the first segment of their path after skipping the given number of them, eg: `1` groups `/v1/pets` in `pets`
+  PathPrefix(usize),
+}
+
+impl FromStr for GroupOperationsBy {
+  type Err = NormalizerError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "tag" => Ok(GroupOperationsBy::FirstTag),
+      "path" => Ok(GroupOperationsBy::PathPrefix(0)),
+      s if s.starts_with("path:") => s["path:".len()..]
+        .parse()
+        .map(GroupOperationsBy::PathPrefix)
+        .map_err(|_| NormalizerError::UnknownGrouping(s.into())),
+      s if s.starts_with("x-") => Ok(GroupOperationsBy::Extension(s.into())),
+      _ => Err(NormalizerError::UnknownGrouping(s.into())),
+    }
+  }
+}
+
+/// Lists the operations of a resolved OpenAPI 3 document, in the order of its `paths` and methods.
+/// Each one has its `method`, `path`, `operationId` (synthesized from the method and path when missing, suffixed with a number when already used), `tags`,
+/// the path and operation `parameters` merged and split by location, `requestBody`, `responses`, `successResponse` and `security`.
+/// Several operations declaring the same `operationId` fail, as they would generate clashing symbols.
+#[::tracing::instrument(level = "trace", skip(json))]
+pub fn openapi3_operations(json: &Value) -> Result<Vec<Value>, NormalizerError> {
+  if !json["openapi"].as_str().is_some_and(|v| v.starts_with('3')) {
+    return Err(NormalizerError::NotOpenapi3);
+  }
+  let all = entries(&json["paths"])
+    .flat_map(|(path, item)| entries(item).map(move |(method, operation)| (path, item, method, operation)))
+    .filter(|(_, _, method, _)| HTTP_METHODS.contains(method))
+    .collect::<Vec<_>>();
+  let mut used_ids = HashSet::new();
+  for (_, _, _, operation) in all.iter() {
+    if let Some(operation_id) = declared_id(operation) {
+      if !used_ids.insert(operation_id.to_string()) {
+        return Err(NormalizerError::DuplicateOperationId(operation_id.into()));
+      }
+    }
+  }
+  let mut operations = vec![];
+  for (path, item, method, operation) in all {
+    let (operation_id, synthesized) = match declared_id(operation) {
+      Some(operation_id) => (operation_id.to_string(), false),
+      None => (unique_id(synthesize_id(&[method, path]), &mut used_ids), true),
+    };
+    operations.push(normalize_operation(json, path, item, method, operation, operation_id, synthesized));
+  }
+  Ok(operations)
+}
+
+/// The `operationId` of an operation, if it is a non empty string
+fn declared_id(operation: &Value) -> Option<&str> {
+  operation["operationId"].as_str().filter(|id| !id.is_empty())
+}
+
+/// The id, suffixed with the first number from 2 making it unused, eg: `getOrdersId2`
+fn unique_id(id: String, used_ids: &mut HashSet<String>) -> String {
+  let mut unique = id.clone();
+  let mut suffix = 2;
+  while used_ids.contains(&unique) {
+    unique = format!("{}{}", id, suffix);
+    suffix += 1;
+  }
+  used_ids.insert(unique.clone());
+  unique
+}
+
+fn normalize_operation(
+  json: &Value,
+  path: &str,
+  item: &Value,
+  method: &str,
+  operation: &Value,
+  operation_id: String,
+  synthesized: bool,
+) -> Value {
+  let parameters = merged_parameters(&item["parameters"], &operation["parameters"]);
+  let by_location = PARAMETER_LOCATIONS
+    .iter()
+    .map(|location| {
+      let located = parameters.iter().filter(|p| p["in"] == *location).cloned().collect();
+      (location.to_string(), Value::Array(located))
+    })
+    .collect::<Map<_, _>>();
+  let extensions = operation
+    .as_object()
+    .into_iter()
+    .flatten()
+    .filter(|(k, _)| k.starts_with("x-"))
+    .map(|(k, v)| (k.clone(), v.clone()))
+    .collect::<Map<_, _>>();
+
+  json!({
+    "method": method,
+    "path": path,
+    "operationId": operation_id,
+    "operationIdSynthesized": synthesized,
+    "summary": first_set(&operation["summary"], &item["summary"]),
+    "description": first_set(&operation["description"], &item["description"]),
+    "tags": or_array(&operation["tags"]),
+    "deprecated": operation["deprecated"].as_bool().unwrap_or(false),
+    "allParameters": parameters,
+    "parameters": by_location,
+    "requestBody": operation["requestBody"],
+    "responses": operation["responses"],
+    "successResponse": success_response(&operation["responses"]),
+    "security": or_array(first_set(&operation["security"], &json["security"])),
+    "servers": or_array(first_set(&operation["servers"], first_set(&item["servers"], &json["servers"]))),
+    "extensions": extensions,
+  })
+}
+
+/// The path item parameters, replaced by the operation ones having the same `name` and `in`, then the other operation ones
+fn merged_parameters(path_parameters: &Value, operation_parameters: &Value) -> Vec<Value> {
+  let operation_parameters = operation_parameters.as_array().map(Vec::as_slice).unwrap_or_default();
+  let same = |a: &Value, b: &Value| a["name"] == b["name"] && a["in"] == b["in"];
+  let mut parameters = path_parameters
+    .as_array()
+    .into_iter()
+    .flatten()
+    .map(|p| operation_parameters.iter().find(|o| same(o, p)).unwrap_or(p).clone())
+    .collect::<Vec<_>>();
+  for parameter in operation_parameters {
+    if !parameters.iter().any(|p| same(p, parameter)) {
+      parameters.push(parameter.clone());
+    }
+  }
+  parameters
+}
+
+/// The first `2XX` response, or the `default` one, with its `status`
+fn success_response(responses: &Value) -> Value {
+  let Some(responses) = responses.as_object() else {
+    return Value::Null;
+  };
+  let mut statuses = responses.keys().filter(|s| s.starts_with('2')).collect::<Vec<_>>();
+  statuses.sort();
+  let status = statuses
+    .first()
+    .copied()
+    .or_else(|| responses.keys().find(|s| *s == DEFAULT_RESPONSE));
+  match status {
+    Some(status) => {
+      let mut response = Map::new();
+      response.insert("status".into(), status.clone().into());
+      response.extend(
+        responses[status]
+          .as_object()
+          .into_iter()
+          .flatten()
+          .map(|(k, v)| (k.clone(), v.clone())),
+      );
+      Value::Object(response)
+    }
+    None => Value::Null,
+  }
+}
+
+/// Groups the normalized operations in `{ tag, description, operations }` objects.
+/// The groups declared in the document `tags` come first, in their order, then the other ones in the order they are met.
+/// Operations without tag, or without path segment left when grouping by path prefix, are in the `default` group.
+#[::tracing::instrument(level = "trace", skip(json, operations))]
+pub fn group_operations(json: &Value, operations: Vec<Value>, group_by: &GroupOperationsBy) -> Vec<Value> {
+  let declared = json["tags"].as_array().map(Vec::as_slice).unwrap_or_default();
+  let mut groups = declared
+    .iter()
+    .filter_map(|t| t["name"].as_str())
+    .map(|name| (name.to_string(), vec![]))
+    .collect::<Vec<(String, Vec<Value>)>>();
+  let mut indexes = groups
+    .iter()
+    .enumerate()
+    .map(|(index, (name, _))| (name.clone(), index))
+    .collect::<HashMap<_, _>>();
+  for operation in operations {
+    let first_tag = operation["tags"][0].as_str();
+    let tag = match group_by {
+      GroupOperationsBy::FirstTag => first_tag,
+      GroupOperationsBy::Extension(extension) => operation["extensions"][extension].as_str().or(first_tag),
+      GroupOperationsBy::PathPrefix(skipped) => operation["path"]
+        .as_str()
+        .and_then(|p| p.split('/').filter(|s| !s.is_empty()).nth(*skipped)),
+    }
+    .unwrap_or(DEFAULT_TAG)
+    .to_string();
+    match indexes.get(&tag) {
+      Some(index) => groups[*index].1.push(operation),
+      None => {
+        indexes.insert(tag.clone(), groups.len());
+        groups.push((tag, vec![operation]));
+      }
+    }
+  }
+
+  groups
+    .into_iter()
+    .filter(|(_, operations)| !operations.is_empty())
+    .map(|(tag, operations)| {
+      let description = declared
+        .iter()
+        .find(|t| t["name"] == tag.as_str())
+        .map(|t| t["description"].clone())
+        .unwrap_or_default();
+      json!({ "tag": tag, "description": description, "operations": operations })
+    })
+    .collect()
+}
+
+fn first_set<'a>(value: &'a Value, fallback: &'a Value) -> &'a Value {
+  match value {
+    Value::Null => fallback,
+    value => value,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::resolver::resolve_sample;
+  use test_case::test_case;
+
+  const STORE: &str = "_samples/openapi3/store.yaml";
+
+  fn operations() -> Result<Vec<Value>, anyhow::Error> {
+    Ok(openapi3_operations(&*resolve_sample(STORE)?)?)
+  }
+
+  #[test]
+  fn operations_should_be_listed_in_order() -> Result<(), anyhow::Error> {
+    let operations = operations()?;
+    let ids = operations
+      .iter()
+      .map(|o| (o["method"].clone(), o["operationId"].clone()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      ids,
+      vec![
+        (json!("get"), json!("listOrders")),
+        (json!("post"), json!("createOrder")),
+        (json!("get"), json!("getOrdersOrderId")),
+        (json!("delete"), json!("deleteOrder")),
+        (json!("get"), json!("health")),
+      ]
+    );
+    assert_eq!(operations[2]["operationIdSynthesized"], true);
+    assert_eq!(operations[3]["extensions"], json!({ "x-controller": "admin" }));
+    Ok(())
+  }
+
+  #[test]
+  fn operation_parameters_should_be_merged_and_split() -> Result<(), anyhow::Error> {
+    let operation = &operations()?[2];
+    let names = operation["allParameters"]
+      .as_array()
+      .map(|p| p.iter().map(|p| p["name"].clone()).collect::<Vec<_>>());
+    assert_eq!(names, Some(vec![json!("orderId"), json!("X-Trace"), json!("session")]));
+    assert_eq!(operation["parameters"]["header"][0]["required"], true);
+    assert_eq!(operation["parameters"]["path"][0]["name"], "orderId");
+    assert_eq!(operation["parameters"]["query"], json!([]));
+    assert_eq!(operation["parameters"]["cookie"][0]["name"], "session");
+    Ok(())
+  }
+
+  #[test]
+  fn operation_responses_and_security_should_be_set() -> Result<(), anyhow::Error> {
+    let operations = operations()?;
+    assert_eq!(operations[0]["successResponse"]["status"], "200");
+    assert_eq!(operations[0]["successResponse"]["description"], "The orders");
+    assert_eq!(operations[2]["successResponse"]["status"], "default");
+    assert_eq!(operations[1]["requestBody"]["required"], true);
+    assert_eq!(operations[0]["security"], json!([{ "api_key": [] }]));
+    assert_eq!(operations[3]["security"], json!([]));
+    assert_eq!(operations[0]["servers"][0]["url"], "https://store.example.com/v1");
+    Ok(())
+  }
+
+  fn assert_groups(groups: &[Value], expected: &[(&str, &[&str])]) {
+    let groups = groups
+      .iter()
+      .map(|g| {
+        let ids = g["operations"].as_array().into_iter().flatten().map(|o| o["operationId"].clone());
+        (g["tag"].clone(), ids.collect::<Vec<_>>())
+      })
+      .collect::<Vec<_>>();
+    let expected = expected
+      .iter()
+      .map(|(tag, ids)| (json!(tag), ids.iter().map(|id| json!(id)).collect::<Vec<_>>()))
+      .collect::<Vec<_>>();
+    assert_eq!(groups, expected);
+  }
+
+  #[test_case(GroupOperationsBy::FirstTag, &[("orders", &["listOrders", "createOrder", "getOrdersOrderId", "deleteOrder"]), ("default", &["health"])])]
+  #[test_case(GroupOperationsBy::Extension("x-controller".into()), &[("orders", &["listOrders", "createOrder", "getOrdersOrderId"]), ("admin", &["deleteOrder"]), ("default", &["health"])])]
+  #[test_case(GroupOperationsBy::PathPrefix(0), &[("orders", &["listOrders", "createOrder", "getOrdersOrderId", "deleteOrder"]), ("health", &["health"])])]
+  fn group_operations_tests(group_by: GroupOperationsBy, expected: &[(&str, &[&str])]) -> Result<(), anyhow::Error> {
+    let groups = group_operations(&*resolve_sample(STORE)?, operations()?, &group_by);
+    assert_groups(&groups, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn group_descriptions_should_be_the_declared_ones() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "openapi": "3.0.3",
+      "tags": [{ "name": "unused" }, { "name": "pets", "description": "The pets" }],
+      "paths": { "/pets": { "get": { "tags": ["pets"] } } }
+    });
+    let groups = group_operations(&json, openapi3_operations(&json)?, &GroupOperationsBy::FirstTag);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["description"], "The pets");
+    Ok(())
+  }
+
+  #[test_case(GroupOperationsBy::PathPrefix(0), &[("v1", &["listPets", "getPet", "listOwners"])])]
+  #[test_case(GroupOperationsBy::PathPrefix(1), &[("pets", &["listPets", "getPet"]), ("owners", &["listOwners"])])]
+  fn versioned_paths_grouping_tests(group_by: GroupOperationsBy, expected: &[(&str, &[&str])]) -> Result<(), anyhow::Error> {
+    let json = json!({
+      "openapi": "3.0.3",
+      "paths": {
+        "/v1/pets": { "get": { "operationId": "listPets" } },
+        "/v1/pets/{petId}": { "get": { "operationId": "getPet" } },
+        "/v1/owners": { "get": { "operationId": "listOwners" } }
+      }
+    });
+    let groups = group_operations(&json, openapi3_operations(&json)?, &group_by);
+    assert_groups(&groups, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn synthesized_operation_ids_should_be_unique() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "openapi": "3.0.3",
+      "paths": {
+        "/orders/{id}": { "get": {} },
+        "/orders/id": { "get": {}, "post": { "operationId": "postOrdersId" } },
+        "/orders/id/": { "get": {}, "post": {} }
+      }
+    });
+    let ids = openapi3_operations(&json)?
+      .iter()
+      .map(|o| o["operationId"].clone())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      ids,
+      vec![
+        json!("getOrdersId"),
+        json!("getOrdersId2"),
+        json!("postOrdersId"),
+        json!("getOrdersId3"),
+        json!("postOrdersId2")
+      ]
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn invalid_operation_ids_should_be_synthesized() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "openapi": "3.0.3",
+      "paths": { "/pets": { "get": { "operationId": 42 }, "post": { "operationId": "" } } }
+    });
+    let operations = openapi3_operations(&json)?;
+    let ids = operations
+      .iter()
+      .map(|o| (o["operationId"].clone(), o["operationIdSynthesized"].clone()))
+      .collect::<Vec<_>>();
+    assert_eq!(ids, vec![(json!("getPets"), json!(true)), (json!("postPets"), json!(true))]);
+    Ok(())
+  }
+
+  #[test]
+  fn duplicated_operation_ids_should_fail() {
+    let json = json!({
+      "openapi": "3.0.3",
+      "paths": { "/pets": { "get": { "operationId": "pets" } }, "/cats": { "get": { "operationId": "pets" } } }
+    });
+    assert!(matches!(
+      openapi3_operations(&json),
+      Err(NormalizerError::DuplicateOperationId(id)) if id == "pets"
+    ));
+  }
+
+  #[test]
+  fn swagger2_documents_should_fail() {
+    assert!(matches!(
+      openapi3_operations(&json!({ "swagger": "2.0" })),
+      Err(NormalizerError::NotOpenapi3)
+    ));
+  }
+}
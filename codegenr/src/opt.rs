@@ -1,22 +1,55 @@
-use crate::{Options, OptionsMap};
+use crate::{
+  loaders::{HttpOptions, YamlDocuments},
+  merger::MergeStrategy,
+  normalizer::Normalization,
+  resolver::CircularRefs,
+  Options, OptionsMap,
+};
 use serde_json::Value;
 use std::fs::read_to_string;
 use structopt::StructOpt;
 
 pub const CODEGENR_CONFIG_FILE: &str = "codegenr.toml";
+/// Top level table of `codegenr.toml` holding the http options of the sections not setting them
+const HTTP_TABLE: &str = "_http";
 
 //https://docs.rs/structopt/latest/structopt/#specifying-argument-types
 #[derive(StructOpt, Debug)]
 #[structopt(name = "codegenr")]
 pub struct Opt {
-  // todo later
-  // #[structopt(
-  //   long,
-  //   short,
-  //   help = "If set, codegenr keeps running and watch for all concerned files change. And then re-run the generation.",
-  //   global = true
-  // )]
-  // pub watch: bool,
+  #[structopt(
+    long,
+    short,
+    help = "If set, codegenr keeps running and watch for all concerned files change. And then re-run the generation.",
+    global = true
+  )]
+  pub watch: bool,
+  #[structopt(
+    long,
+    help = "If set, codegenr writes nothing and fails if the generated files differ from the existing ones.",
+    global = true,
+    conflicts_with = "watch"
+  )]
+  pub check: bool,
+  #[structopt(
+    long,
+    help = "If set, codegenr writes nothing and prints what each `FILE` and `CLEAN` instruction would do.",
+    global = true,
+    conflicts_with_all = &["watch", "check"]
+  )]
+  pub dry_run: bool,
+  #[structopt(
+    long,
+    help = "If set, codegenr stops at the first failing section instead of running all the remaining ones.",
+    global = true
+  )]
+  pub fail_fast: bool,
+  #[structopt(
+    long,
+    help = "If set, the remote documents are only taken from the remote documents cache, and nothing is downloaded.",
+    global = true
+  )]
+  pub offline: bool,
   #[structopt(subcommand)]
   pub cmd: Option<Command>,
 }
@@ -34,33 +67,28 @@ pub enum Command {
     file: String,
   },
   #[structopt(name = "gen", help = "Executes one generation from command line parameters.")]
-  FromLine {
+  FromLine(Box<FromLineOpt>),
+  #[structopt(
+    name = "bundle",
+    help = "Writes a multi-files json/yaml/openapi document in one file, its external `$ref`s being rewritten as internal ones."
+  )]
+  Bundle {
     #[structopt(long, short, help = "Source json/yaml/openapi file.")]
     source: String,
-    #[structopt(long, short, help = "Output folder.")]
-    output: String,
     #[structopt(
       long,
       short,
-      help = "Templates folder(s), in which only one .hbs file should have no `_` as prefix (Underscored templates are partial templates)."
+      help = "Output file, written as yaml if it has a `.yaml` or `.yml` extension, as json otherwise."
     )]
-    templates: Vec<String>,
+    output: String,
+    #[structopt(long, help = "Folder where the downloaded remote documents are cached.")]
+    remote_cache: Option<String>,
     #[structopt(
       long,
-      short,
-      help = "Optional path to a file where the intermediate json representation of resolved source(s) will be output. 
-        The resolved json will be output as <file>.resolved.json, the full text rendered result will be output as <file>.rendered.txt."
+      help = "Config file whose `[_http]` table tells how the remote documents are requested, if it exists.",
+      default_value = CODEGENR_CONFIG_FILE
     )]
-    intermediate: Option<String>,
-    #[structopt(long, short, help = "Path to custom helper files.")]
-    custom_helpers: Vec<String>,
-    #[structopt(
-        long,
-        short,
-        help = "Global parameters values formatted `key=value`. Values will be parsed as json or strings if the json parsing fails.",
-        parse(try_from_str = parse_key_val)
-      )]
-    global_parameters: Vec<(String, serde_json::Value)>,
+    file: String,
   },
 }
 
@@ -72,6 +100,95 @@ impl Default for Command {
   }
 }
 
+/// The parameters of the `gen` command
+#[derive(StructOpt, Debug)]
+pub struct FromLineOpt {
+  #[structopt(long, short, help = "Source json/yaml/openapi file.", default_value = "")]
+  pub source: String,
+  #[structopt(
+    long,
+    help = "Other source files, or glob patterns like `specs/*.yaml`, merged with the `source` one."
+  )]
+  pub sources: Vec<String>,
+  #[structopt(
+    long,
+    help = "How the source documents are merged: `deep`, `by_file_stem` or `concat`.",
+    default_value = "deep"
+  )]
+  pub merge: MergeStrategy,
+  #[structopt(long, short, help = "Output folder.")]
+  pub output: String,
+  #[structopt(
+    long,
+    short,
+    help = "Templates folder(s), in which only one .hbs file should have no `_` as prefix (Underscored templates are partial templates)."
+  )]
+  pub templates: Vec<String>,
+  #[structopt(
+    long,
+    short,
+    help = "Optional path to a file where the intermediate json representation of resolved source(s) will be output. 
+        The resolved json will be output as <file>.resolved.json, the full text rendered result will be output as <file>.rendered.txt."
+  )]
+  pub intermediate: Option<String>,
+  #[structopt(long, short, help = "Path to custom helper files.")]
+  pub custom_helpers: Vec<String>,
+  #[structopt(
+      long,
+      short,
+      help = "Global parameters values formatted `key=value`. Values will be parsed as json or strings if the json parsing fails.",
+      parse(try_from_str = parse_key_val)
+    )]
+  pub global_parameters: Vec<(String, serde_json::Value)>,
+  #[structopt(
+    long,
+    help = "Optional json pointer, in which `*` matches all the children of a node. The main template is rendered once for each matching node, with `template`, `key`, `node` and `root` in its context."
+  )]
+  pub for_each: Option<String>,
+  #[structopt(
+    long,
+    help = "Optional template of the file path each `for_each` rendering is written to, eg: `models/{{to_snake_case key}}.rs`."
+  )]
+  pub output_path: Option<String>,
+  #[structopt(
+    long,
+    help = "If set, every non underscored .hbs template is a main template, rendered against the same source."
+  )]
+  pub multiple_main_templates: bool,
+  #[structopt(
+    long,
+    help = "If set, the templates of a templates folder replace the same named ones (and the main one) of the previous folders."
+  )]
+  pub override_templates: bool,
+  #[structopt(
+    long,
+    help = "What to do with circular `$ref`s: `error`, `placeholder` to leave the `$ref` as is, `depth=<N>` to follow them N times, or `link` to resolve each `$ref` once and replace the circular and later ones with a `x-recursive` marker.",
+    default_value = "error"
+  )]
+  pub circular_refs: CircularRefs,
+  #[structopt(
+    long,
+    help = "Key under which a `$ref`ed value that is not an object is kept when the `$ref` has sibling keys. If not set, the sibling keys are dropped."
+  )]
+  pub ref_value_key: Option<String>,
+  #[structopt(
+    long,
+    help = "Folder where the downloaded remote documents are cached, and revalidated with their `ETag` or `Last-Modified` headers."
+  )]
+  pub remote_cache: Option<String>,
+  #[structopt(
+    long,
+    help = "How a yaml source holding several `---` separated documents is loaded: `single`, `array`, or `merge=<key1>,<key2>...` to put each document under a key.",
+    default_value = "single"
+  )]
+  pub yaml_documents: YamlDocuments,
+  #[structopt(
+    long,
+    help = "Transformations of the resolved source done before rendering it, in their order: `asyncapi` adds its channels, operations, messages and servers under `x-codegenr.asyncapi`, `openapi3` converts a Swagger 2.0 source to the OpenAPI 3 layout, `operations` adds the flat list of the OpenAPI 3 operations under `x-codegenr.operations`, `tags` adds them grouped by their first tag under `x-codegenr.tags` (`tags=path` groups them by the first segment of their path, `tags=path:<n>` by the segment following the first n ones, eg: `tags=path:1` groups `/v1/pets` in `pets`, `tags=x-<extension>` by extension)."
+  )]
+  pub normalize: Vec<Normalization>,
+}
+
 // From here: https://github.com/TeXitoi/structopt/blob/master/examples/keyvalue.rs
 // Parse a single key-value pair
 fn parse_key_val(s: &str) -> Result<(String, Value), anyhow::Error> {
@@ -85,6 +202,33 @@ fn parse_key_val(s: &str) -> Result<(String, Value), anyhow::Error> {
   ))
 }
 
+/// Parses the `codegenr.toml` sections. The `[_http]` table is not a section, but the http options of the sections not setting them.
+fn parse_config(config: &str) -> Result<OptionsMap, toml::de::Error> {
+  let mut table: toml::Table = toml::from_str(config)?;
+  let http = take_http_options(&mut table)?;
+  let mut options_map: OptionsMap = table.try_into()?;
+  for options in options_map.values_mut() {
+    if options.http.is_none() {
+      options.http = http.clone();
+    }
+  }
+  Ok(options_map)
+}
+
+fn take_http_options(table: &mut toml::Table) -> Result<Option<HttpOptions>, toml::de::Error> {
+  table.remove(HTTP_TABLE).map(|http| http.try_into()).transpose()
+}
+
+/// The `[_http]` table of a `codegenr.toml` file, if the file exists
+pub fn config_http_options(file: &str) -> Result<Option<HttpOptions>, anyhow::Error> {
+  if !std::path::Path::new(file).exists() {
+    return Ok(None);
+  }
+  let mut table: toml::Table =
+    toml::from_str(&read_to_string(file)?).map_err(|e| anyhow::anyhow!("Unable to deserialize `{}` config file: `{}`.", file, e))?;
+  Ok(take_http_options(&mut table)?)
+}
+
 impl TryFrom<Command> for OptionsMap {
   type Error = anyhow::Error;
 
@@ -98,29 +242,124 @@ impl TryFrom<Command> for OptionsMap {
             e
           )
         })?;
-        let opts: OptionsMap =
-          toml::from_str(&config).map_err(|e| anyhow::anyhow!("Unable to deserialize `{}` config file: `{}`.", file, e))?;
-        Ok(opts)
+        parse_config(&config).map_err(|e| anyhow::anyhow!("Unable to deserialize `{}` config file: `{}`.", file, e))
       }
-      Command::FromLine {
-        source,
-        output,
-        templates,
-        intermediate,
-        custom_helpers,
-        global_parameters,
-      } => {
+      Command::FromLine(opt) => {
+        let FromLineOpt {
+          source,
+          sources,
+          merge,
+          output,
+          templates,
+          intermediate,
+          custom_helpers,
+          global_parameters,
+          for_each,
+          output_path,
+          multiple_main_templates,
+          override_templates,
+          circular_refs,
+          ref_value_key,
+          remote_cache,
+          yaml_documents,
+          normalize,
+        } = *opt;
         let options = Options {
           source,
+          sources,
+          merge,
           output,
           templates,
           intermediate,
           custom_helpers,
           global_parameters: global_parameters.into_iter().collect(),
+          for_each,
+          output_path,
+          multiple_main_templates,
+          override_templates,
+          circular_refs,
+          ref_value_key,
+          remote_cache,
+          offline: false,
+          http: None,
+          yaml_documents,
+          normalize,
         };
         let map = OptionsMap::from_iter(std::iter::once(("command_line".into(), options)));
         Ok(map)
       }
+      Command::Bundle { .. } => Err(anyhow::anyhow!("The `bundle` command does not execute any generation.")),
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_config_should_use_the_global_http_options_for_sections_not_setting_them() -> Result<(), anyhow::Error> {
+    let config = r#"
+      [_http]
+      bearer_token = "${TOKEN}"
+
+      [api]
+      source = "api.yaml"
+      output = "."
+      templates = []
+      custom_helpers = []
+      global_parameters = {}
+
+      [models]
+      source = "models.yaml"
+      output = "."
+      templates = []
+      custom_helpers = []
+      global_parameters = {}
+      http = { timeout_secs = 10 }
+    "#;
+    let options_map = parse_config(config)?;
+    assert_eq!(options_map.keys().collect::<Vec<_>>(), vec!["api", "models"]);
+    assert_eq!(
+      options_map["api"].http.as_ref().and_then(|h| h.bearer_token.as_deref()),
+      Some("${TOKEN}")
+    );
+    assert_eq!(options_map["models"].http.as_ref().and_then(|h| h.bearer_token.as_deref()), None);
+    assert_eq!(options_map["models"].http.as_ref().and_then(|h| h.timeout_secs), Some(10));
+    Ok(())
+  }
+
+  #[test]
+  fn gen_command_should_be_parsed() -> Result<(), anyhow::Error> {
+    let opt = Opt::from_iter_safe([
+      "codegenr",
+      "gen",
+      "--source",
+      "api.yaml",
+      "--output",
+      "out",
+      "--normalize",
+      "tags=path:1",
+    ])?;
+    let options_map: OptionsMap = opt.cmd.unwrap_or_default().try_into()?;
+    let options = &options_map["command_line"];
+    assert_eq!((options.source.as_str(), options.output.as_str()), ("api.yaml", "out"));
+    assert_eq!(options.normalize, vec!["tags=path:1".parse()?]);
+    Ok(())
+  }
+
+  #[test]
+  fn a_section_can_be_named_http() -> Result<(), anyhow::Error> {
+    let config = r#"
+      [http]
+      source = "http.yaml"
+      output = "."
+      templates = []
+      custom_helpers = []
+      global_parameters = {}
+    "#;
+    let options_map = parse_config(config)?;
+    assert_eq!(options_map["http"].source, "http.yaml");
+    Ok(())
+  }
+}
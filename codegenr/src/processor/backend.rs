@@ -0,0 +1,328 @@
+use super::ProcessorError;
+use crate::filesystem::{create_file_from_path, make_path_from_root};
+use glob::glob;
+use std::{
+  cell::RefCell,
+  collections::BTreeMap,
+  io::Write,
+  path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// Where the `FILE` and `CLEAN` instructions read, write and delete files.
+/// All the paths are relative to the output root of the backend.
+pub trait OutputBackend: std::fmt::Debug {
+  /// Full path of a file, as displayed to the user
+  fn full_path(&self, relative_path: &str) -> PathBuf;
+  /// Content of an existing file, if any
+  fn read_file(&self, relative_path: &str) -> Option<String>;
+  fn write_file(&self, relative_path: &str, content: &str) -> Result<(), ProcessorError>;
+  /// Full paths of the files matching the pattern, or contained in a folder matching it
+  fn find_files(&self, pattern: &str) -> Result<Vec<PathBuf>, ProcessorError>;
+  /// Deletes all the files and folders matching the pattern
+  fn clean(&self, pattern: &str) -> Result<(), ProcessorError>;
+  /// Outputs a line of a `CONSOLE` instruction
+  fn write_console(&self, line: &str) -> Result<(), ProcessorError> {
+    println!("{line}");
+    Ok(())
+  }
+  /// Called once all the instructions have been executed
+  fn flush(&self) -> Result<(), ProcessorError> {
+    Ok(())
+  }
+}
+
+/// Tells if a `CLEAN` pattern matches a file relative to the output root, or a folder containing it
+pub(crate) fn clean_pattern_matches(pattern: &glob::Pattern, relative_path: &str) -> bool {
+  let options = glob::MatchOptions {
+    require_literal_separator: true,
+    ..Default::default()
+  };
+  Path::new(relative_path).ancestors().any(|p| pattern.matches_path_with(p, options))
+}
+
+/// Reads and writes the files on disk, under an output folder
+#[derive(Debug)]
+pub struct DiskBackend {
+  output_folder: String,
+}
+
+impl DiskBackend {
+  pub fn new(output_folder: impl Into<String>) -> Self {
+    Self {
+      output_folder: output_folder.into(),
+    }
+  }
+
+  fn glob(&self, pattern: &str) -> Result<Vec<PathBuf>, ProcessorError> {
+    let full_path_pattern = Path::new(&self.output_folder).join(pattern);
+    let str_pattern = full_path_pattern.to_str().ok_or(ProcessorError::PathBufToStrConvert)?;
+    Ok(glob(str_pattern)?.flatten().collect())
+  }
+}
+
+impl OutputBackend for DiskBackend {
+  fn full_path(&self, relative_path: &str) -> PathBuf {
+    make_path_from_root(&self.output_folder, relative_path)
+  }
+
+  fn read_file(&self, relative_path: &str) -> Option<String> {
+    std::fs::read_to_string(self.full_path(relative_path)).ok()
+  }
+
+  fn write_file(&self, relative_path: &str, content: &str) -> Result<(), ProcessorError> {
+    let mut file = create_file_from_path(&self.full_path(relative_path))?;
+    Ok(file.write_all(content.as_bytes())?)
+  }
+
+  fn find_files(&self, pattern: &str) -> Result<Vec<PathBuf>, ProcessorError> {
+    let mut files = vec![];
+    for path in self.glob(pattern)? {
+      if path.is_dir() {
+        let entries = WalkDir::new(path).into_iter().flatten().filter(|e| e.file_type().is_file());
+        files.extend(entries.map(|e| e.into_path()));
+      } else if path.is_file() {
+        files.push(path);
+      }
+    }
+    Ok(files)
+  }
+
+  fn clean(&self, pattern: &str) -> Result<(), ProcessorError> {
+    for path in self.glob(pattern)? {
+      if path.is_dir() {
+        std::fs::remove_dir_all(path)?
+      } else if path.is_file() {
+        std::fs::remove_file(path)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Keeps the files, the console output and the clean patterns in memory,
+/// to generate in a temporary overlay or as a test double
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MemoryBackend {
+  files: RefCell<BTreeMap<String, String>>,
+  console: RefCell<Vec<String>>,
+  clean_patterns: RefCell<Vec<String>>,
+}
+
+impl MemoryBackend {
+  pub fn new(files: BTreeMap<String, String>) -> Self {
+    Self {
+      files: RefCell::new(files),
+      ..Default::default()
+    }
+  }
+
+  /// All the files contents, by relative path
+  pub fn files(&self) -> BTreeMap<String, String> {
+    self.files.borrow().clone()
+  }
+
+  /// All the lines output by the `CONSOLE` instructions
+  pub fn console(&self) -> Vec<String> {
+    self.console.borrow().clone()
+  }
+
+  /// Patterns of the `CLEAN` instructions, in their order
+  pub fn clean_patterns(&self) -> Vec<String> {
+    self.clean_patterns.borrow().clone()
+  }
+
+  fn matching_files(&self, pattern: &str) -> Result<Vec<String>, ProcessorError> {
+    let pattern = glob::Pattern::new(pattern)?;
+    let files = self.files.borrow();
+    Ok(files.keys().filter(|file| clean_pattern_matches(&pattern, file)).cloned().collect())
+  }
+}
+
+impl OutputBackend for MemoryBackend {
+  fn full_path(&self, relative_path: &str) -> PathBuf {
+    PathBuf::from(relative_path)
+  }
+
+  fn read_file(&self, relative_path: &str) -> Option<String> {
+    self.files.borrow().get(relative_path).cloned()
+  }
+
+  fn write_file(&self, relative_path: &str, content: &str) -> Result<(), ProcessorError> {
+    self.files.borrow_mut().insert(relative_path.into(), content.into());
+    Ok(())
+  }
+
+  fn find_files(&self, pattern: &str) -> Result<Vec<PathBuf>, ProcessorError> {
+    Ok(self.matching_files(pattern)?.into_iter().map(PathBuf::from).collect())
+  }
+
+  fn clean(&self, pattern: &str) -> Result<(), ProcessorError> {
+    let matching = self.matching_files(pattern)?;
+    let mut files = self.files.borrow_mut();
+    for file in matching {
+      files.remove(&file);
+    }
+    self.clean_patterns.borrow_mut().push(pattern.into());
+    Ok(())
+  }
+
+  fn write_console(&self, line: &str) -> Result<(), ProcessorError> {
+    self.console.borrow_mut().push(line.into());
+    Ok(())
+  }
+}
+
+/// All the output of a generation, once it is done
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GeneratedOutput {
+  /// The files contents, by relative path
+  pub files: BTreeMap<String, String>,
+  /// The lines output by the `CONSOLE` instructions
+  pub console: Vec<String>,
+  /// The patterns of the `CLEAN` instructions, in their order
+  pub clean_patterns: Vec<String>,
+}
+
+impl From<MemoryBackend> for GeneratedOutput {
+  fn from(backend: MemoryBackend) -> Self {
+    Self {
+      files: backend.files.into_inner(),
+      console: backend.console.into_inner(),
+      clean_patterns: backend.clean_patterns.into_inner(),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArchiveFormat {
+  Tar,
+  Zip,
+}
+
+/// Collects the files in memory, and writes them all in a tar or zip archive once processed
+#[derive(Debug)]
+pub struct ArchiveBackend {
+  archive_path: PathBuf,
+  format: ArchiveFormat,
+  memory: MemoryBackend,
+}
+
+impl ArchiveBackend {
+  pub fn new(archive_path: impl Into<PathBuf>, format: ArchiveFormat) -> Self {
+    Self {
+      archive_path: archive_path.into(),
+      format,
+      memory: Default::default(),
+    }
+  }
+
+  fn write_tar(&self, file: std::fs::File) -> Result<(), ProcessorError> {
+    let mut builder = tar::Builder::new(file);
+    for (path, content) in self.memory.files() {
+      let mut header = tar::Header::new_gnu();
+      header.set_size(content.len() as u64);
+      header.set_mode(0o644);
+      header.set_cksum();
+      builder.append_data(&mut header, path, content.as_bytes())?;
+    }
+    builder.finish()?;
+    Ok(())
+  }
+
+  fn write_zip(&self, file: std::fs::File) -> Result<(), ProcessorError> {
+    let mut writer = zip::ZipWriter::new(file);
+    for (path, content) in self.memory.files() {
+      writer.start_file(path, zip::write::FileOptions::default())?;
+      writer.write_all(content.as_bytes())?;
+    }
+    writer.finish()?;
+    Ok(())
+  }
+}
+
+impl OutputBackend for ArchiveBackend {
+  fn full_path(&self, relative_path: &str) -> PathBuf {
+    self.archive_path.join(relative_path)
+  }
+
+  fn read_file(&self, relative_path: &str) -> Option<String> {
+    self.memory.read_file(relative_path)
+  }
+
+  fn write_file(&self, relative_path: &str, content: &str) -> Result<(), ProcessorError> {
+    self.memory.write_file(relative_path, content)
+  }
+
+  fn find_files(&self, pattern: &str) -> Result<Vec<PathBuf>, ProcessorError> {
+    let files = self.memory.matching_files(pattern)?;
+    Ok(files.iter().map(|f| self.full_path(f)).collect())
+  }
+
+  fn clean(&self, pattern: &str) -> Result<(), ProcessorError> {
+    self.memory.clean(pattern)
+  }
+
+  fn flush(&self) -> Result<(), ProcessorError> {
+    let file = create_file_from_path(&self.archive_path)?;
+    match self.format {
+      ArchiveFormat::Tar => self.write_tar(file),
+      ArchiveFormat::Zip => self.write_zip(file),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Read;
+  use tempdir::TempDir;
+
+  #[test]
+  fn memory_backend_clean_should_remove_matching_files_and_folders() -> anyhow::Result<()> {
+    let backend = MemoryBackend::default();
+    backend.write_file("plop.rs", "")?;
+    backend.write_file("sub/plop.rs", "")?;
+    backend.write_file("sub/plop.txt", "")?;
+    backend.write_file("other/plop.txt", "")?;
+
+    assert_eq!(backend.find_files("*.rs")?, vec![PathBuf::from("plop.rs")]);
+    backend.clean("**/*.rs")?;
+    backend.clean("sub")?;
+    assert_eq!(backend.files().into_keys().collect::<Vec<_>>(), vec!["other/plop.txt".to_string()]);
+    Ok(())
+  }
+
+  #[test]
+  fn archive_backend_should_write_a_tar_file() -> anyhow::Result<()> {
+    let tmp = TempDir::new("ARCHIVE_tests")?;
+    let archive_path = tmp.path().join("output.tar");
+    let backend = ArchiveBackend::new(&archive_path, ArchiveFormat::Tar);
+    backend.write_file("sub/plop.rs", "hello")?;
+    backend.flush()?;
+
+    let mut archive = tar::Archive::new(std::fs::File::open(&archive_path)?);
+    let mut entries = archive.entries()?;
+    let mut entry = entries.next().expect("There should be one entry.")?;
+    assert_eq!(entry.path()?, Path::new("sub/plop.rs"));
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    assert_eq!(content, "hello");
+    Ok(())
+  }
+
+  #[test]
+  fn archive_backend_should_write_a_zip_file() -> anyhow::Result<()> {
+    let tmp = TempDir::new("ARCHIVE_tests")?;
+    let archive_path = tmp.path().join("output.zip");
+    let backend = ArchiveBackend::new(&archive_path, ArchiveFormat::Zip);
+    backend.write_file("sub/plop.rs", "hello")?;
+    backend.flush()?;
+
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&archive_path)?)?;
+    let mut content = String::new();
+    archive.by_name("sub/plop.rs")?.read_to_string(&mut content)?;
+    assert_eq!(content, "hello");
+    Ok(())
+  }
+}
@@ -1,16 +1,15 @@
 use super::*;
-use glob::glob;
-use std::path::Path;
 
 pub const CLEAN: &str = "CLEAN";
 
 pub struct CleanInstruction {
-  output_folder: String,
+  backend: Rc<dyn OutputBackend>,
+  target: ProcessorTarget,
 }
 
 impl CleanInstruction {
-  pub fn new(output_folder: String) -> Self {
-    Self { output_folder }
+  pub fn new(backend: Rc<dyn OutputBackend>, target: ProcessorTarget) -> Self {
+    Self { backend, target }
   }
 }
 
@@ -19,16 +18,13 @@ impl Instruction for CleanInstruction {
     CLEAN
   }
   fn start(&self, params: Vec<String>) -> Result<Box<dyn InstructionLineHandler>, ProcessorError> {
-    let pattern = params.get(0).ok_or(ProcessorError::InstructionParameterMissing(CLEAN, "pattern"))?;
+    let pattern = params
+      .first()
+      .ok_or(ProcessorError::InstructionParameterMissing(CLEAN, "pattern"))?;
 
-    let full_path_pattern = Path::new(&self.output_folder).join(pattern);
-    let str_pattern = full_path_pattern.to_str().ok_or(ProcessorError::PathBufToStrConvert)?;
-    for path in glob(str_pattern)?.flatten() {
-      if path.is_dir() {
-        std::fs::remove_dir_all(path)?
-      } else if path.is_file() {
-        std::fs::remove_file(path)?;
-      }
+    match &self.target {
+      ProcessorTarget::Write => self.backend.clean(pattern)?,
+      ProcessorTarget::DryRun(dry_run) => dry_run.borrow_mut().record_clean(pattern, self.backend.find_files(pattern)?)?,
     }
     Ok(Box::new(TranscientLineHandler) as Box<dyn InstructionLineHandler>)
   }
@@ -42,7 +38,7 @@ mod tests {
 
   #[test]
   pub fn clean_not_existing_path_should_not_fail() -> anyhow::Result<()> {
-    let instruction = CleanInstruction::new("NonExistingPath".into());
+    let instruction = CleanInstruction::new(Rc::new(DiskBackend::new("NonExistingPath")), ProcessorTarget::Write);
     instruction.start(vec!["plop.txt".into()])?;
     Ok(())
   }
@@ -50,7 +46,7 @@ mod tests {
   #[test]
   pub fn clean_a_single_file() -> anyhow::Result<()> {
     let tmp = TempDir::new("CLEAN_tests")?;
-    let instruction = CleanInstruction::new(tmp.path().to_string_lossy().into());
+    let instruction = CleanInstruction::new(Rc::new(DiskBackend::new(tmp.path().to_string_lossy())), ProcessorTarget::Write);
     let (_, file_path) = create_file(tmp.path(), "plop.rs")?;
     assert!(file_path.exists());
     instruction.start(vec!["plop.rs".into()])?;
@@ -61,7 +57,7 @@ mod tests {
   #[test]
   pub fn clean_a_pattern() -> anyhow::Result<()> {
     let tmp = TempDir::new("CLEAN_tests")?;
-    let instruction = CleanInstruction::new(tmp.path().to_string_lossy().into());
+    let instruction = CleanInstruction::new(Rc::new(DiskBackend::new(tmp.path().to_string_lossy())), ProcessorTarget::Write);
     let (_, file_path1) = create_file(tmp.path(), "plop.rs")?;
     let (_, file_path2) = create_file(tmp.path(), "sub/plop.rs")?;
     let (_, file_path3) = create_file(tmp.path(), "sub/plop.txt")?;
@@ -75,11 +71,24 @@ mod tests {
   #[test]
   pub fn clean_a_directory() -> anyhow::Result<()> {
     let tmp = TempDir::new("CLEAN_tests")?;
-    let instruction = CleanInstruction::new(tmp.path().to_string_lossy().into());
+    let instruction = CleanInstruction::new(Rc::new(DiskBackend::new(tmp.path().to_string_lossy())), ProcessorTarget::Write);
     let dir_path = create_dir(tmp.path(), "directory")?;
     assert!(dir_path.exists() && dir_path.is_dir());
     instruction.start(vec!["directory".into()])?;
     assert!(!dir_path.exists());
     Ok(())
   }
+
+  #[test]
+  pub fn dry_run_should_record_cleaned_files_without_deleting() -> anyhow::Result<()> {
+    let tmp = TempDir::new("CLEAN_tests")?;
+    let dry_run = SharedPlanRecorder::default();
+    let backend: Rc<dyn OutputBackend> = Rc::new(DiskBackend::new(tmp.path().to_string_lossy()));
+    let instruction = CleanInstruction::new(backend.clone(), ProcessorTarget::DryRun(dry_run.clone()));
+    let (_, file_path) = create_file(tmp.path(), "sub/plop.rs")?;
+    instruction.start(vec!["sub".into()])?;
+    assert!(file_path.exists());
+    assert_eq!(dry_run.take().into_plan(&*backend).actions, vec![PlannedAction::Deleted(file_path)]);
+    Ok(())
+  }
 }
@@ -2,25 +2,36 @@ use super::*;
 
 pub const CONSOLE: &str = "CONSOLE";
 
-pub struct ConsoleInstruction;
+pub struct ConsoleInstruction {
+  backend: Rc<dyn OutputBackend>,
+}
+
+impl ConsoleInstruction {
+  pub fn new(backend: Rc<dyn OutputBackend>) -> Self {
+    Self { backend }
+  }
+}
 
 impl Instruction for ConsoleInstruction {
   fn command_name(&self) -> &'static str {
     CONSOLE
   }
   fn start(&self, _params: Vec<String>) -> Result<Box<dyn InstructionLineHandler>, ProcessorError> {
-    Ok(Box::new(ConsoleLineHandler) as Box<dyn InstructionLineHandler>)
+    Ok(Box::new(ConsoleLineHandler {
+      backend: self.backend.clone(),
+    }) as Box<dyn InstructionLineHandler>)
   }
   fn needs_closing(&self) -> bool {
     true
   }
 }
 
-pub struct ConsoleLineHandler;
+pub struct ConsoleLineHandler {
+  backend: Rc<dyn OutputBackend>,
+}
 
 impl InstructionLineHandler for ConsoleLineHandler {
   fn handle_line(&self, line: &str) -> Result<(), ProcessorError> {
-    println!("{}", line);
-    Ok(())
+    self.backend.write_console(line)
   }
 }
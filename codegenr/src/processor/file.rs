@@ -1,16 +1,16 @@
 use super::*;
-use crate::filesystem::{create_file_from_path, make_path_from_root};
-use std::{cell::RefCell, fmt::Write, path::PathBuf};
+use std::{cell::RefCell, fmt::Write};
 
 pub const FILE: &str = "FILE";
 
 pub struct FileInstruction {
-  output_folder: String,
+  backend: Rc<dyn OutputBackend>,
+  target: ProcessorTarget,
 }
 
 impl FileInstruction {
-  pub fn new(output_folder: String) -> Self {
-    Self { output_folder }
+  pub fn new(backend: Rc<dyn OutputBackend>, target: ProcessorTarget) -> Self {
+    Self { backend, target }
   }
 }
 
@@ -20,9 +20,9 @@ impl Instruction for FileInstruction {
   }
   fn start(&self, params: Vec<String>) -> Result<Box<dyn InstructionLineHandler>, ProcessorError> {
     let file_path = params
-      .get(0)
+      .first()
       .ok_or(ProcessorError::InstructionParameterMissing(FILE, "file_name"))?;
-    Ok(Box::new(FileLineHandler::new(&self.output_folder, file_path)?) as Box<dyn InstructionLineHandler>)
+    Ok(Box::new(FileLineHandler::new(self.backend.clone(), file_path, self.target.clone())?) as Box<dyn InstructionLineHandler>)
   }
   fn needs_closing(&self) -> bool {
     true
@@ -30,16 +30,19 @@ impl Instruction for FileInstruction {
 }
 
 pub struct FileLineHandler {
-  file_path: PathBuf,
+  relative_path: String,
+  backend: Rc<dyn OutputBackend>,
   buffer: RefCell<String>,
+  target: ProcessorTarget,
 }
 
 impl FileLineHandler {
-  fn new(output_folder: &str, write_file_path: &str) -> Result<Self, ProcessorError> {
-    let file_path = make_path_from_root(output_folder, write_file_path);
+  fn new(backend: Rc<dyn OutputBackend>, write_file_path: &str, target: ProcessorTarget) -> Result<Self, ProcessorError> {
     Ok(Self {
-      file_path,
+      relative_path: write_file_path.into(),
+      backend,
       buffer: RefCell::new(Default::default()),
+      target,
     })
   }
 }
@@ -54,18 +57,22 @@ impl InstructionLineHandler for FileLineHandler {
 impl Drop for FileLineHandler {
   fn drop(&mut self) {
     let buffer = self.buffer.borrow();
-    if let Ok(content) = std::fs::read_to_string(&self.file_path) {
-      if content == buffer.as_str() {
-        tracing::warn!("File content is the same, not writing it again : {}", self.file_path.display());
+    match &self.target {
+      ProcessorTarget::Write => {}
+      ProcessorTarget::DryRun(dry_run) => {
+        dry_run.borrow_mut().record_file(self.relative_path.clone(), buffer.clone());
         return;
       }
     }
 
-    if let Ok(mut file) = create_file_from_path(&self.file_path).map_err(|e| {
-      tracing::error!("Error creating file: {}", e);
-      e
-    }) {
-      let _ignored = std::io::Write::write_all(&mut file, buffer.as_bytes());
+    if FileStatus::of(&*self.backend, &self.relative_path, buffer.as_str()) == FileStatus::Unchanged {
+      let file_path = self.backend.full_path(&self.relative_path);
+      tracing::warn!("File content is the same, not writing it again : {}", file_path.display());
+      return;
+    }
+
+    if let Err(e) = self.backend.write_file(&self.relative_path, buffer.as_str()) {
+      tracing::error!("Error writing file: {}", e);
     }
   }
 }
@@ -79,7 +86,7 @@ mod tests {
   #[test]
   pub fn start_not_existing_file_should_create_file() -> anyhow::Result<()> {
     let tmp = TempDir::new("FILE_tests")?;
-    let instruction = FileInstruction::new(tmp.path().to_string_lossy().into());
+    let instruction = FileInstruction::new(Rc::new(DiskBackend::new(tmp.path().to_string_lossy())), ProcessorTarget::Write);
     let handler = instruction.start(vec!["sub/plop.txt".into()])?;
     let (_file, should_exists_path) = create_file(tmp.path(), "sub/plop.txt")?;
     assert!(should_exists_path.exists());
@@ -90,4 +97,32 @@ mod tests {
     assert_eq!(content, "hello ...\n");
     Ok(())
   }
+
+  #[test]
+  pub fn dry_run_should_record_file_actions_without_writing() -> anyhow::Result<()> {
+    let tmp = TempDir::new("FILE_tests")?;
+    let dry_run = SharedPlanRecorder::default();
+    let backend: Rc<dyn OutputBackend> = Rc::new(DiskBackend::new(tmp.path().to_string_lossy()));
+    let instruction = FileInstruction::new(backend.clone(), ProcessorTarget::DryRun(dry_run.clone()));
+    let (mut file, existing_path) = create_file(tmp.path(), "existing.txt")?;
+    std::io::Write::write_all(&mut file, b"hello ...\n")?;
+
+    let handler = instruction.start(vec!["existing.txt".into()])?;
+    handler.handle_line("hello ...")?;
+    drop(handler);
+    let handler = instruction.start(vec!["missing.txt".into()])?;
+    handler.handle_line("hello ...")?;
+    drop(handler);
+
+    assert!(!tmp.path().join("missing.txt").exists());
+    assert_eq!(
+      dry_run.take().into_plan(&*backend).actions,
+      vec![
+        PlannedAction::Unchanged(existing_path.clone()),
+        PlannedAction::Created(tmp.path().join("missing.txt"))
+      ]
+    );
+    assert_eq!(std::fs::read_to_string(existing_path)?, "hello ...\n");
+    Ok(())
+  }
 }
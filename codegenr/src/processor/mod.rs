@@ -1,13 +1,17 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
+mod backend;
 mod clean;
 mod console;
 mod file;
+mod plan;
 
+pub use backend::*;
 use clean::*;
 use console::*;
 use file::*;
 use glob::PatternError;
+pub use plan::*;
 use thiserror::Error;
 
 static INSTRUCTION_LINE_REGEX: once_cell::sync::Lazy<regex::Regex> =
@@ -33,6 +37,8 @@ pub enum ProcessorError {
   InstructionParameterMissing(&'static str, &'static str),
   #[error("Error converting PathBuf to str.")]
   PathBufToStrConvert,
+  #[error("Zip Error: `{0}`.")]
+  Zip(#[from] zip::result::ZipError),
 }
 
 pub trait Instruction {
@@ -55,17 +61,63 @@ impl InstructionLineHandler for TranscientLineHandler {
   }
 }
 
-fn get_instructions(output: String) -> HashMap<&'static str, Box<dyn Instruction>> {
+/// What the `FILE` and `CLEAN` instructions do with the output backend. `CONSOLE` lines always go to it.
+#[derive(Clone)]
+pub enum ProcessorTarget {
+  /// Files are written and cleaned through the output backend
+  Write,
+  /// Nothing is written nor cleaned, the file actions are recorded
+  DryRun(SharedPlanRecorder),
+}
+
+fn get_instructions(backend: Rc<dyn OutputBackend>, target: ProcessorTarget) -> HashMap<&'static str, Box<dyn Instruction>> {
   let mut hash: HashMap<&'static str, Box<dyn Instruction>> = HashMap::<_, _>::with_capacity(3);
-  hash.insert(CLEAN, Box::new(CleanInstruction::new(output.clone())) as Box<dyn Instruction>);
-  hash.insert(FILE, Box::new(FileInstruction::new(output)) as Box<dyn Instruction>);
-  hash.insert(CONSOLE, Box::new(ConsoleInstruction) as Box<dyn Instruction>);
+  hash.insert(
+    CLEAN,
+    Box::new(CleanInstruction::new(backend.clone(), target.clone())) as Box<dyn Instruction>,
+  );
+  hash.insert(
+    FILE,
+    Box::new(FileInstruction::new(backend.clone(), target)) as Box<dyn Instruction>,
+  );
+  hash.insert(CONSOLE, Box::new(ConsoleInstruction::new(backend)) as Box<dyn Instruction>);
   hash
 }
 
 #[::tracing::instrument(level = "trace")]
-pub fn process(content: &str, output: String) -> Result<(), ProcessorError> {
-  let instructions = get_instructions(output);
+pub fn process(content: &str, backend: Rc<dyn OutputBackend>) -> Result<(), ProcessorError> {
+  let instructions = get_instructions(backend.clone(), ProcessorTarget::Write);
+  execute(content, instructions)?;
+  backend.flush()
+}
+
+/// Processes the content without touching the backend, and returns the actions
+/// the `FILE` and `CLEAN` instructions would have executed
+#[::tracing::instrument(level = "trace")]
+pub fn plan(content: &str, backend: Rc<dyn OutputBackend>) -> Result<Plan, ProcessorError> {
+  let recorder = SharedPlanRecorder::default();
+  let instructions = get_instructions(backend.clone(), ProcessorTarget::DryRun(recorder.clone()));
+  execute(content, instructions)?;
+  Ok(recorder.take().into_plan(&*backend))
+}
+
+/// Processes the content without touching the backend, and reports the differences
+/// between what would be generated and what exists in it
+#[::tracing::instrument(level = "trace")]
+pub fn check(content: &str, backend: Rc<dyn OutputBackend>) -> Result<CheckReport, ProcessorError> {
+  plan(content, backend).map(Into::into)
+}
+
+/// Processes the content in a memory backend, without touching the disk nor the console,
+/// and returns all the produced output
+#[::tracing::instrument(level = "trace")]
+pub fn generate(content: &str) -> Result<GeneratedOutput, ProcessorError> {
+  let backend = Rc::new(MemoryBackend::default());
+  process(content, backend.clone())?;
+  Ok(Rc::try_unwrap(backend).unwrap_or_else(|backend| (*backend).clone()).into())
+}
+
+fn execute(content: &str, instructions: HashMap<&'static str, Box<dyn Instruction>>) -> Result<(), ProcessorError> {
   let mut active_handlers = HashMap::<String, Box<dyn InstructionLineHandler>>::new();
 
   for (line_number, line) in content.lines().enumerate() {
@@ -117,6 +169,127 @@ pub fn process(content: &str, output: String) -> Result<(), ProcessorError> {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::filesystem::save_file_content;
+  use tempdir::TempDir;
+
+  #[test]
+  fn check_test() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("CHECK_tests")?;
+    std::fs::create_dir(tmp.path().join("sub"))?;
+    save_file_content(tmp.path(), "same.rs", "same\n")?;
+    save_file_content(tmp.path(), "changed.rs", "before\n")?;
+    save_file_content(tmp.path(), "sub/stale.rs", "stale\n")?;
+    save_file_content(tmp.path(), "sub/handwritten.txt", "handwritten\n")?;
+
+    let report = check(
+      r#"### CLEAN **/*.rs
+### FILE same.rs
+same
+### /FILE
+### FILE changed.rs
+after
+### /FILE
+### FILE missing.rs
+missing
+### /FILE"#,
+      Rc::new(DiskBackend::new(tmp.path().to_string_lossy())),
+    )?;
+
+    assert_eq!(
+      report,
+      CheckReport {
+        changed: vec![tmp.path().join("changed.rs")],
+        missing: vec![tmp.path().join("missing.rs")],
+        extra: vec![tmp.path().join("sub/stale.rs")],
+      }
+    );
+    assert_eq!(std::fs::read_to_string(tmp.path().join("changed.rs"))?, "before\n");
+    assert!(tmp.path().join("sub/stale.rs").exists());
+    Ok(())
+  }
+
+  #[test]
+  fn plan_test() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("PLAN_tests")?;
+    save_file_content(tmp.path(), "changed.rs", "line1\nbefore\n")?;
+    save_file_content(tmp.path(), "stale.rs", "stale\n")?;
+
+    let plan = plan(
+      r#"### CLEAN *.rs
+### FILE changed.rs
+line1
+after
+### /FILE"#,
+      Rc::new(DiskBackend::new(tmp.path().to_string_lossy())),
+    )?;
+
+    let changed = tmp.path().join("changed.rs");
+    let diff = format!(
+      "--- a/{0}\n+++ b/{0}\n@@ -1,2 +1,2 @@\n line1\n-before\n+after\n",
+      changed.display()
+    );
+    assert_eq!(
+      plan.actions,
+      vec![
+        PlannedAction::Deleted(tmp.path().join("stale.rs")),
+        PlannedAction::Modified(changed.clone(), diff),
+      ]
+    );
+    assert_eq!(std::fs::read_to_string(changed)?, "line1\nbefore\n");
+    Ok(())
+  }
+
+  #[test]
+  fn plan_should_replay_the_instructions_in_order() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("PLAN_tests")?;
+    save_file_content(tmp.path(), "existing.rs", "existing\n")?;
+    save_file_content(tmp.path(), "kept.txt", "kept\n")?;
+
+    let plan = plan(
+      r#"### FILE kept.txt
+kept
+### /FILE
+### FILE existing.rs
+existing
+### /FILE
+### FILE temporary.rs
+temporary
+### /FILE
+### CLEAN *.rs"#,
+      Rc::new(DiskBackend::new(tmp.path().to_string_lossy())),
+    )?;
+
+    assert_eq!(
+      plan.actions,
+      vec![
+        PlannedAction::Unchanged(tmp.path().join("kept.txt")),
+        PlannedAction::Deleted(tmp.path().join("existing.rs")),
+      ]
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn generate_test() -> Result<(), anyhow::Error> {
+    let generated = generate(
+      r#"### CLEAN **/*.rs
+### FILE sub/plop.rs
+test
+### /FILE
+### CONSOLE
+Hello
+### /CONSOLE"#,
+    )?;
+
+    assert_eq!(
+      generated.files,
+      [("sub/plop.rs".to_string(), "test\n".to_string())].into_iter().collect()
+    );
+    assert_eq!(generated.console, vec!["Hello".to_string()]);
+    assert_eq!(generated.clean_patterns, vec!["**/*.rs".to_string()]);
+    assert!(!std::path::Path::new("sub/plop.rs").exists());
+    Ok(())
+  }
 
   #[test]
   #[ignore]
@@ -134,7 +307,7 @@ Hello
 test2
 ### / FILE
     "#,
-      ".".into(),
+      Rc::new(DiskBackend::new(".")),
     )?;
 
     Ok(())
@@ -0,0 +1,175 @@
+use super::{clean_pattern_matches, OutputBackend, ProcessorError};
+use similar::TextDiff;
+use std::{cell::RefCell, fmt::Display, path::PathBuf, rc::Rc};
+
+pub type SharedPlanRecorder = Rc<RefCell<PlanRecorder>>;
+
+/// State of a generated file compared to the one existing in the output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+  Unchanged,
+  Modified { existing: String },
+  Missing,
+}
+
+impl FileStatus {
+  pub fn of(backend: &dyn OutputBackend, relative_path: &str, content: &str) -> Self {
+    match backend.read_file(relative_path) {
+      Some(existing) if existing == content => FileStatus::Unchanged,
+      Some(existing) => FileStatus::Modified { existing },
+      None => FileStatus::Missing,
+    }
+  }
+}
+
+/// An executed instruction, as recorded
+#[derive(Debug)]
+enum RecordedAction {
+  /// A `FILE` relative path and content
+  File(String, String),
+  /// A `CLEAN` pattern and the full paths of the existing files it matches
+  Clean(glob::Pattern, Vec<PathBuf>),
+}
+
+/// Records what the `FILE` and `CLEAN` instructions would have done, instead of doing it
+#[derive(Debug, Default)]
+pub struct PlanRecorder {
+  recorded: Vec<RecordedAction>,
+}
+
+impl PlanRecorder {
+  pub fn record_file(&mut self, relative_path: String, content: String) {
+    self.recorded.push(RecordedAction::File(relative_path, content));
+  }
+
+  pub fn record_clean(&mut self, pattern: &str, files: Vec<PathBuf>) -> Result<(), ProcessorError> {
+    self.recorded.push(RecordedAction::Clean(glob::Pattern::new(pattern)?, files));
+    Ok(())
+  }
+
+  /// Replays the recorded instructions in their order, and compares their outcome to the backend content.
+  /// Each file has one action, the one of the last instruction touching it: a file cleaned then generated again
+  /// is reported as a generated one, and a file generated then cleaned as a deleted one, or not at all if it did not exist.
+  pub fn into_plan(self, backend: &dyn OutputBackend) -> Plan {
+    let mut actions = Vec::<PlannedAction>::with_capacity(self.recorded.len());
+    let mut generated = Vec::<String>::new();
+    for recorded in self.recorded {
+      match recorded {
+        RecordedAction::File(relative_path, content) => {
+          let path = backend.full_path(&relative_path);
+          actions.retain(|a| a.path() != &path);
+          actions.push(file_action(backend, &relative_path, &content, path));
+          generated.push(relative_path);
+        }
+        RecordedAction::Clean(pattern, existing) => {
+          let cleaned = generated
+            .iter()
+            .filter(|g| clean_pattern_matches(&pattern, g))
+            .map(|g| backend.full_path(g))
+            .chain(existing.iter().cloned())
+            .collect::<Vec<_>>();
+          actions.retain(|a| !cleaned.contains(a.path()));
+          actions.extend(existing.into_iter().map(PlannedAction::Deleted));
+        }
+      }
+    }
+    Plan { actions }
+  }
+}
+
+fn file_action(backend: &dyn OutputBackend, relative_path: &str, content: &str, path: PathBuf) -> PlannedAction {
+  match FileStatus::of(backend, relative_path, content) {
+    FileStatus::Unchanged => PlannedAction::Unchanged(path),
+    FileStatus::Modified { existing } => {
+      let old_header = format!("a/{}", path.display());
+      let new_header = format!("b/{}", path.display());
+      let diff = TextDiff::from_lines(existing.as_str(), content)
+        .unified_diff()
+        .header(&old_header, &new_header)
+        .to_string();
+      PlannedAction::Modified(path, diff)
+    }
+    FileStatus::Missing => PlannedAction::Created(path),
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedAction {
+  Created(PathBuf),
+  /// The modified file path and the unified diff of its changes
+  Modified(PathBuf, String),
+  Unchanged(PathBuf),
+  Deleted(PathBuf),
+}
+
+impl PlannedAction {
+  pub fn path(&self) -> &PathBuf {
+    match self {
+      PlannedAction::Created(path) | PlannedAction::Modified(path, _) | PlannedAction::Unchanged(path) | PlannedAction::Deleted(path) => {
+        path
+      }
+    }
+  }
+}
+
+/// All the file system actions the processor would execute
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Plan {
+  pub actions: Vec<PlannedAction>,
+}
+
+impl Display for Plan {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for action in self.actions.iter() {
+      match action {
+        PlannedAction::Created(path) => writeln!(f, "  created: {}", path.display())?,
+        PlannedAction::Modified(path, diff) => writeln!(f, "  modified: {}\n{}", path.display(), diff)?,
+        PlannedAction::Unchanged(path) => writeln!(f, "  unchanged: {}", path.display())?,
+        PlannedAction::Deleted(path) => writeln!(f, "  deleted: {}", path.display())?,
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Differences between what would be generated and what exists on disk.
+/// `extra` files are the ones a `CLEAN` instruction would delete, and that would not be generated again:
+/// the other files of the output folder can't be told apart from hand-written ones, so they are not reported.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CheckReport {
+  pub changed: Vec<PathBuf>,
+  pub missing: Vec<PathBuf>,
+  pub extra: Vec<PathBuf>,
+}
+
+impl CheckReport {
+  pub fn is_up_to_date(&self) -> bool {
+    self.changed.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+  }
+}
+
+impl From<Plan> for CheckReport {
+  fn from(plan: Plan) -> Self {
+    let mut report = CheckReport::default();
+    for action in plan.actions {
+      match action {
+        PlannedAction::Created(path) => report.missing.push(path),
+        PlannedAction::Modified(path, _) => report.changed.push(path),
+        PlannedAction::Unchanged(_) => {}
+        PlannedAction::Deleted(path) => report.extra.push(path),
+      }
+    }
+    report
+  }
+}
+
+impl Display for CheckReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for (kind, paths) in [("changed", &self.changed), ("missing", &self.missing), ("extra", &self.extra)] {
+      for path in paths {
+        writeln!(f, "  {kind}: {}", path.display())?;
+      }
+    }
+    Ok(())
+  }
+}
@@ -1,6 +1,7 @@
 use handlebars::{Handlebars, TemplateError};
 use std::collections::HashMap;
 use thiserror::Error;
+use tracing::debug;
 use walkdir::WalkDir;
 
 const PARTIAL_TEMPLATE_PREFIX: &str = "_";
@@ -9,63 +10,99 @@ const HANDLEBARS_TEMPLATE_EXTENSION: &str = ".hbs";
 #[derive(Error, Debug)]
 pub enum RenderError {
   #[error("Template error: `{0}`.")]
-  Template(#[from] TemplateError),
+  Template(Box<TemplateError>),
   #[error("Walkdir error: `{0}`.")]
   Walkdir(#[from] walkdir::Error),
   #[error("2 main templates were found : `{0}` and `{1}`. There should be only one in all the template directories.")]
   TwoMainTemp(String, String),
   #[error("2 partial templates are named `{0}` they should have unique names.")]
   UniqueNameTemp(String),
+  #[error("2 main templates are named `{0}` they should have unique names.")]
+  UniqueNameMainTemp(String),
   #[error("No main template has been detected, we don't know what to execute.")]
   NoMainTemp,
 }
 
+impl From<TemplateError> for RenderError {
+  fn from(error: TemplateError) -> Self {
+    RenderError::Template(Box::new(error))
+  }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TemplateCollection {
-  main: Template,
+  mains: Vec<Template>,
   partials: HashMap<String, Template>,
 }
 
 impl TemplateCollection {
+  /// Collects the templates, failing if there is not exactly one main template
   pub fn from_list(templates: impl IntoIterator<Item = Template>) -> Result<TemplateCollection, RenderError> {
-    let mut main: Option<Template> = None;
+    Self::collect(templates, false)
+  }
+
+  /// Collects the templates, in which every non partial template is a main template
+  pub fn from_list_with_multiple_mains(templates: impl IntoIterator<Item = Template>) -> Result<TemplateCollection, RenderError> {
+    Self::collect(templates, true)
+  }
+
+  fn collect(templates: impl IntoIterator<Item = Template>, multiple_mains: bool) -> Result<TemplateCollection, RenderError> {
+    let mut mains: Vec<Template> = vec![];
     let mut partials = HashMap::<String, Template>::new();
 
     for t in templates {
       match t.template_type() {
         TemplateType::Main => {
-          if let Some(existing) = main.as_ref() {
+          if let Some(existing) = mains.first().filter(|_| !multiple_mains) {
             return Err(RenderError::TwoMainTemp(
               existing.template_name().to_string(),
               t.template_name().to_string(),
             ));
           };
-          main = Some(t);
+          if mains.iter().any(|m| m.template_name() == t.template_name()) {
+            return Err(RenderError::UniqueNameMainTemp(t.template_name().into()));
+          }
+          mains.push(t);
         }
         TemplateType::Partial => {
-          if let Some(existing) = partials.get(t.template_name()) {
-            return Err(RenderError::UniqueNameTemp(existing.template_name().into()));
+          let qualified_name = t.qualified_name();
+          if partials.contains_key(&qualified_name) {
+            return Err(RenderError::UniqueNameTemp(qualified_name));
           };
-          partials.insert(t.template_name().into(), t);
+          partials.insert(qualified_name, t);
         }
       }
     }
 
-    let main = main.ok_or(RenderError::NoMainTemp)?;
+    if mains.is_empty() {
+      return Err(RenderError::NoMainTemp);
+    }
+    mains.sort_by(|a, b| a.template_name().cmp(b.template_name()));
 
-    Ok(Self { main, partials })
+    Ok(Self { mains, partials })
   }
 
   pub fn setup_handlebars(&self, handlebars: &mut Handlebars) -> Result<(), RenderError> {
-    handlebars.register_template_file(self.main.template_name(), self.main.file_path())?;
-    for (_, value) in self.partials.iter() {
-      handlebars.register_template_file(value.template_name(), value.file_path())?
+    for main in self.mains.iter() {
+      handlebars.register_template_file(main.template_name(), main.file_path())?;
+    }
+    let mut short_names = HashMap::<&str, usize>::new();
+    for partial in self.partials.values() {
+      *short_names.entry(partial.template_name()).or_default() += 1;
+    }
+    for (qualified_name, value) in self.partials.iter() {
+      handlebars.register_template_file(qualified_name, value.file_path())?;
+      let short_name = value.template_name();
+      if short_name != qualified_name && short_names.get(short_name) == Some(&1) {
+        handlebars.register_template_file(short_name, value.file_path())?;
+      }
     }
     Ok(())
   }
 
-  pub fn main_template_name(&self) -> &str {
-    self.main.template_name()
+  /// Names of the main templates, sorted alphabetically
+  pub fn main_template_names(&self) -> Vec<&str> {
+    self.mains.iter().map(|m| m.template_name()).collect()
   }
 }
 
@@ -80,6 +117,7 @@ pub struct Template {
   template_type: TemplateType,
   file_name: String,
   file_path: String,
+  namespace: String,
 }
 
 impl Template {
@@ -88,9 +126,16 @@ impl Template {
       template_type,
       file_name: file_name.into(),
       file_path: file_path.into(),
+      namespace: Default::default(),
     }
   }
 
+  /// Sets the sub folder path of the template, relative to its templates folder, `/` separated
+  pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+    self.namespace = namespace.into();
+    self
+  }
+
   pub fn file_path(&self) -> &str {
     &self.file_path
   }
@@ -99,11 +144,49 @@ impl Template {
     self.file_name.trim_start_matches('_').trim_end_matches(".hbs")
   }
 
+  /// The template name prefixed by its sub folder path, eg: `models/plop` for `models/_plop.hbs`
+  pub fn qualified_name(&self) -> String {
+    if self.namespace.is_empty() {
+      self.template_name().into()
+    } else {
+      format!("{}/{}", self.namespace, self.template_name())
+    }
+  }
+
   pub fn template_type(&self) -> TemplateType {
     self.template_type
   }
 }
 
+/// Merges the templates of several folders, in which the templates of a later folder replace the ones
+/// with the same name from the earlier folders. If there can be only one main template,
+/// the main template of a later folder replaces the earlier one, whatever their names.
+pub fn override_templates(folders: impl IntoIterator<Item = Vec<Template>>, multiple_mains: bool) -> Vec<Template> {
+  let overrides = |existing: &Template, t: &Template| match (existing.template_type(), t.template_type()) {
+    (TemplateType::Main, TemplateType::Main) => !multiple_mains || existing.template_name() == t.template_name(),
+    (TemplateType::Partial, TemplateType::Partial) => existing.qualified_name() == t.qualified_name(),
+    _ => false,
+  };
+
+  let mut merged: Vec<(usize, Template)> = vec![];
+  for (layer, templates) in folders.into_iter().enumerate() {
+    for t in templates {
+      match merged.iter_mut().find(|(l, existing)| *l < layer && overrides(existing, &t)) {
+        Some(overridden) => {
+          debug!("`{}` overrides `{}`.", t.file_path(), overridden.1.file_path());
+          *overridden = (layer, t);
+        }
+        None => merged.push((layer, t)),
+      }
+    }
+  }
+
+  for (_, t) in merged.iter() {
+    debug!("`{}` template is `{}`.", t.qualified_name(), t.file_path());
+  }
+  merged.into_iter().map(|(_, t)| t).collect()
+}
+
 pub fn get_templates_from_directory(dir_path: &str) -> Result<Vec<Template>, RenderError> {
   let mut result = vec![];
   for entry in WalkDir::new(dir_path) {
@@ -125,7 +208,14 @@ pub fn get_templates_from_directory(dir_path: &str) -> Result<Vec<Template>, Ren
       } else {
         TemplateType::Main
       };
-      result.push(Template::new(t, file_name, file_path));
+      let namespace = entry
+        .path()
+        .strip_prefix(dir_path)
+        .ok()
+        .and_then(|relative| relative.parent())
+        .map(|folder| folder.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/"))
+        .unwrap_or_default();
+      result.push(Template::new(t, file_name, file_path).with_namespace(namespace));
     }
   }
   Ok(result)
@@ -134,7 +224,8 @@ pub fn get_templates_from_directory(dir_path: &str) -> Result<Vec<Template>, Ren
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::helpers::*;
+  use crate::{filesystem::save_file_content, helpers::*};
+  use tempdir::TempDir;
 
   #[test]
   fn handlebars_loading_test() -> Result<(), anyhow::Error> {
@@ -143,8 +234,7 @@ mod tests {
     let mut h = Handlebars::new();
     handlebars_stateless_setup(&mut h);
     handlebars_statefull_setup(&mut h, Default::default());
-    let result = collection.setup_handlebars(&mut h)?;
-    dbg!(result);
+    collection.setup_handlebars(&mut h)?;
     Ok(())
   }
 
@@ -160,8 +250,8 @@ mod tests {
           "./_samples/render/templates\\_other_partial.hbs",
         ),
         Template::new(TemplateType::Partial, "_partial.hbs", "./_samples/render/templates\\_partial.hbs"),
-        Template::new(TemplateType::Partial, "_plop.hbs", "./_samples/render/templates\\sub\\_plop.hbs"),
-        Template::new(TemplateType::Main, "plop.hbs", "./_samples/render/templates\\sub\\plop.hbs"),
+        Template::new(TemplateType::Partial, "_plop.hbs", "./_samples/render/templates\\sub\\_plop.hbs").with_namespace("sub"),
+        Template::new(TemplateType::Main, "plop.hbs", "./_samples/render/templates\\sub\\plop.hbs").with_namespace("sub"),
         Template::new(TemplateType::Main, "test.hbs", "./_samples/render/templates\\test.hbs"),
       ]
     } else {
@@ -172,8 +262,8 @@ mod tests {
           "./_samples/render/templates/_other_partial.hbs",
         ),
         Template::new(TemplateType::Partial, "_partial.hbs", "./_samples/render/templates/_partial.hbs"),
-        Template::new(TemplateType::Partial, "_plop.hbs", "./_samples/render/templates/sub/_plop.hbs"),
-        Template::new(TemplateType::Main, "plop.hbs", "./_samples/render/templates/sub/plop.hbs"),
+        Template::new(TemplateType::Partial, "_plop.hbs", "./_samples/render/templates/sub/_plop.hbs").with_namespace("sub"),
+        Template::new(TemplateType::Main, "plop.hbs", "./_samples/render/templates/sub/plop.hbs").with_namespace("sub"),
         Template::new(TemplateType::Main, "test.hbs", "./_samples/render/templates/test.hbs"),
       ]
     };
@@ -181,8 +271,9 @@ mod tests {
     // dbg!(&templates, &expected);
     assert_eq!(templates, expected);
 
-    let first = templates.get(0).expect("?");
+    let first = templates.first().expect("?");
     assert_eq!(first.template_name(), "other_partial");
+    assert_eq!(templates[2].qualified_name(), "sub/plop");
     Ok(())
   }
 
@@ -222,6 +313,69 @@ mod tests {
     );
   }
 
+  #[test]
+  fn partials_should_be_registered_with_their_qualified_names() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("NAMESPACES_tests")?;
+    let root = tmp.path().to_string_lossy();
+    for folder in ["models", "clients", "clients/http"] {
+      std::fs::create_dir(tmp.path().join(folder))?;
+    }
+    save_file_content(
+      tmp.path(),
+      "main.hbs",
+      "{{> models/name}} {{> clients/name}} {{> clients/http/verb}} {{> verb}}",
+    )?;
+    save_file_content(tmp.path(), "models/_name.hbs", "model")?;
+    save_file_content(tmp.path(), "clients/_name.hbs", "client")?;
+    save_file_content(tmp.path(), "clients/http/_verb.hbs", "get")?;
+
+    let collection = TemplateCollection::from_list(get_templates_from_directory(&root)?)?;
+    let mut h = Handlebars::new();
+    collection.setup_handlebars(&mut h)?;
+    assert!(h.get_template("name").is_none());
+    assert_eq!(h.render("main", &())?, "model client get get");
+    Ok(())
+  }
+
+  #[test]
+  fn override_templates_should_replace_earlier_folders_templates() -> Result<(), anyhow::Error> {
+    let base = vec![
+      Template::new(TemplateType::Main, "main.hbs", "base/main.hbs"),
+      Template::new(TemplateType::Partial, "_plop.hbs", "base/_plop.hbs"),
+      Template::new(TemplateType::Partial, "_plop.hbs", "base/sub/_plop.hbs").with_namespace("sub"),
+      Template::new(TemplateType::Partial, "_other.hbs", "base/_other.hbs"),
+    ];
+    let custom = vec![
+      Template::new(TemplateType::Main, "custom.hbs", "custom/custom.hbs"),
+      Template::new(TemplateType::Partial, "_plop.hbs", "custom/sub/_plop.hbs").with_namespace("sub"),
+    ];
+
+    let templates = override_templates(vec![base.clone(), custom.clone()], false);
+    let files = templates.iter().map(|t| t.file_path()).collect::<Vec<_>>();
+    assert_eq!(
+      files,
+      vec!["custom/custom.hbs", "base/_plop.hbs", "custom/sub/_plop.hbs", "base/_other.hbs"]
+    );
+    TemplateCollection::from_list(templates)?;
+
+    let templates = override_templates(vec![base, custom], true);
+    assert_eq!(templates.iter().filter(|t| t.template_type() == TemplateType::Main).count(), 2);
+    Ok(())
+  }
+
+  #[test]
+  fn override_templates_should_not_override_templates_of_the_same_folder() {
+    let folder = vec![
+      Template::new(TemplateType::Main, "main.hbs", "base/main.hbs"),
+      Template::new(TemplateType::Main, "other.hbs", "base/other.hbs"),
+    ];
+    let templates = override_templates(vec![folder], false);
+    assert!(matches!(
+      TemplateCollection::from_list(templates),
+      Err(RenderError::TwoMainTemp(_, _))
+    ));
+  }
+
   #[test]
   fn from_list_fails_with_no_main_found() {
     let list = vec![
@@ -252,9 +406,35 @@ mod tests {
       Template::new(TemplateType::Partial, "_partial.hbs", "./_samples/render/templates/_partial.hbs"),
     );
     let expected = TemplateCollection {
-      main: Template::new(TemplateType::Main, "plop.hbs", "./_samples/render/templates/sub/plop.hbs"),
+      mains: vec![Template::new(
+        TemplateType::Main,
+        "plop.hbs",
+        "./_samples/render/templates/sub/plop.hbs",
+      )],
       partials: map,
     };
     assert_eq!(test, expected);
   }
+
+  #[test]
+  fn from_list_with_multiple_mains_success() -> Result<(), anyhow::Error> {
+    let list = vec![
+      Template::new(TemplateType::Main, "test.hbs", "./_samples/render/templates/test.hbs"),
+      Template::new(TemplateType::Partial, "_partial.hbs", "./_samples/render/templates/_partial.hbs"),
+      Template::new(TemplateType::Main, "plop.hbs", "./_samples/render/templates/sub/plop.hbs"),
+    ];
+    let collection = TemplateCollection::from_list_with_multiple_mains(list)?;
+    assert_eq!(collection.main_template_names(), vec!["plop", "test"]);
+    Ok(())
+  }
+
+  #[test]
+  fn from_list_with_multiple_mains_fails_with_same_names_mains() {
+    let list = vec![
+      Template::new(TemplateType::Main, "plop.hbs", "./_samples/render/templates/sub/plop.hbs"),
+      Template::new(TemplateType::Main, "plop.hbs", "./_samples/render/templates/sub2/plop.hbs"),
+    ];
+    let err = TemplateCollection::from_list_with_multiple_mains(list).expect_err("Should be an error");
+    assert!(matches!(err, RenderError::UniqueNameMainTemp(name) if name == "plop"));
+  }
 }
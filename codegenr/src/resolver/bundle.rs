@@ -0,0 +1,278 @@
+use super::{
+  document_key, ensure_orignal_json, escape_pointer_token, fetch_reference_value, unescape_pointer_token, IdsIndex, RefInfo, ResolverError,
+  PATH_SEP, REF, SHARP_SEP,
+};
+use crate::{
+  loaders::{DocumentPath, LoadOptions},
+  OriginalDocumentsHash,
+};
+use percent_encoding::percent_decode_str;
+use serde_json::{Map, Value};
+use std::{
+  collections::{HashMap, HashSet},
+  path::Path,
+};
+
+/// Sections values can be hoisted to at the same place they have in their own document
+const SWAGGER_SECTIONS: [&str; 3] = ["definitions", "parameters", "responses"];
+const OPENAPI_SECTION: &str = "components";
+
+/// State of a document bundling
+struct Bundle<'a> {
+  root_doc: &'a DocumentPath,
+  root: &'a Value,
+  load_options: &'a LoadOptions,
+  /// Internal pointer of the hoisted values, by `$ref` id
+  hoisted_refs: HashMap<String, String>,
+  /// Hoisted values, with their location in the bundled document
+  hoisted: Vec<(Vec<String>, Value)>,
+  /// Pointers already given to hoisted values
+  taken: HashSet<String>,
+  /// `$id` and `$anchor` declarations of the documents met so far
+  ids: IdsIndex,
+}
+
+/// Makes a self-contained document of a multi-files one: the values referenced by the external `$ref`s
+/// are copied into the document sections (`components` or swagger `definitions`), and the `$ref`s are
+/// rewritten as internal `#/...` ones. Unlike `resolve_refs`, the internal `$ref`s are left as is.
+#[::tracing::instrument(level = "trace")]
+pub fn bundle_refs(
+  document: &DocumentPath,
+  load_options: &LoadOptions,
+  original_cache: &mut OriginalDocumentsHash,
+) -> Result<Value, ResolverError> {
+  let root = ensure_orignal_json(document, load_options, original_cache)?;
+  let mut bundled = (*root).clone();
+  let mut bundle = Bundle {
+    root_doc: document,
+    root: &root,
+    load_options,
+    hoisted_refs: Default::default(),
+    hoisted: Default::default(),
+    taken: Default::default(),
+    ids: Default::default(),
+  };
+  bundle.ids.index(document, &root);
+  bundle.rewrite_refs(document, &mut bundled, "", original_cache)?;
+  for (location, value) in bundle.hoisted {
+    insert_at(&mut bundled, &location, value);
+  }
+  Ok(bundled)
+}
+
+impl<'a> Bundle<'a> {
+  /// Rewrites the `$ref`s of a json value, located at the pointer in the current document
+  fn rewrite_refs(
+    &mut self,
+    current_doc: &DocumentPath,
+    json: &mut Value,
+    pointer: &str,
+    original_cache: &mut OriginalDocumentsHash,
+  ) -> Result<(), ResolverError> {
+    match json {
+      Value::Array(a) => {
+        for (i, v) in a.iter_mut().enumerate() {
+          self.rewrite_refs(current_doc, v, &format!("{pointer}{PATH_SEP}{i}"), original_cache)?;
+        }
+      }
+      Value::Object(obj) => {
+        for (key, value) in obj.iter_mut() {
+          match (key.as_str(), value) {
+            (REF, Value::String(ref_value)) => *ref_value = self.internal_ref(current_doc, pointer, ref_value, original_cache)?,
+            (REF, _) => return Err(ResolverError::ShouldBeString),
+            (key, value) => {
+              let pointer = format!("{pointer}{PATH_SEP}{}", escape_pointer_token(key));
+              self.rewrite_refs(current_doc, value, &pointer, original_cache)?
+            }
+          }
+        }
+      }
+      _ => {}
+    }
+    Ok(())
+  }
+
+  /// The internal `$ref` to use in the bundled document, hoisting the referenced value if needed
+  fn internal_ref(
+    &mut self,
+    current_doc: &DocumentPath,
+    pointer: &str,
+    ref_value: &str,
+    original_cache: &mut OriginalDocumentsHash,
+  ) -> Result<String, ResolverError> {
+    let ref_info = match self.ids.lookup(current_doc, pointer, ref_value) {
+      Some(target) => RefInfo::from_target(current_doc, target),
+      None => RefInfo::parse(current_doc, ref_value)?,
+    };
+    if ref_info.document_path == *self.root_doc {
+      return Ok(format!("{SHARP_SEP}{}", ref_info.path.unwrap_or_default()));
+    }
+
+    let ref_id = ref_info.id();
+    if let Some(pointer) = self.hoisted_refs.get(&ref_id) {
+      return Ok(format!("{SHARP_SEP}{pointer}"));
+    }
+
+    let location = self.hoisting_location(&ref_info);
+    let pointer = to_pointer(&location);
+    self.hoisted_refs.insert(ref_id, pointer.clone());
+
+    // The slot is taken before the value refs are rewritten, to keep the values in the order they are met
+    let slot = self.hoisted.len();
+    self.hoisted.push((location, Value::Null));
+    let json = ensure_orignal_json(&ref_info.document_path, self.load_options, original_cache)?;
+    self.ids.index(&ref_info.document_path, &json);
+    let mut value = fetch_reference_value(&json, &ref_info.path)?;
+    self.rewrite_refs(&ref_info.document_path, &mut value, &ref_info.pointer(), original_cache)?;
+    self.hoisted[slot].1 = value;
+    Ok(format!("{SHARP_SEP}{pointer}"))
+  }
+
+  /// Keeps the section the value has in its document if it is a known one, otherwise uses the
+  /// schemas one. The value name is suffixed if it is already taken.
+  fn hoisting_location(&mut self, ref_info: &RefInfo) -> Vec<String> {
+    let tokens = ref_info.path.as_deref().map(pointer_tokens).unwrap_or_default();
+    let (section, name) = match tokens.as_slice() {
+      [section, name] if SWAGGER_SECTIONS.contains(&section.as_str()) => (vec![section.clone()], name.clone()),
+      [components, kind, name] if components == OPENAPI_SECTION => (vec![components.clone(), kind.clone()], name.clone()),
+      _ => {
+        let section = match self.root.get("swagger") {
+          Some(_) => vec![SWAGGER_SECTIONS[0].to_string()],
+          None => vec![OPENAPI_SECTION.to_string(), "schemas".to_string()],
+        };
+        let name = match tokens.last() {
+          Some(name) => name.clone(),
+          None => Path::new(document_key(&ref_info.document_path))
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        };
+        (section, name)
+      }
+    };
+
+    let mut suffix = 1;
+    loop {
+      let candidate = if suffix == 1 { name.clone() } else { format!("{name}{suffix}") };
+      let mut location = section.clone();
+      location.push(candidate);
+      let pointer = to_pointer(&location);
+      if !self.taken.contains(&pointer) && self.root.pointer(&pointer).is_none() {
+        self.taken.insert(pointer);
+        return location;
+      }
+      suffix += 1;
+    }
+  }
+}
+
+fn pointer_tokens(path: &str) -> Vec<String> {
+  let path = percent_decode_str(path).decode_utf8_lossy();
+  path.split(PATH_SEP).filter(|t| !t.is_empty()).map(unescape_pointer_token).collect()
+}
+
+fn to_pointer(location: &[String]) -> String {
+  location.iter().map(|t| format!("{PATH_SEP}{}", escape_pointer_token(t))).collect()
+}
+
+fn insert_at(json: &mut Value, location: &[String], value: Value) {
+  let mut part = json;
+  for token in location {
+    if !part.is_object() {
+      *part = Value::Object(Map::new());
+    }
+    part = part
+      .as_object_mut()
+      .expect("Part was just made an object")
+      .entry(token.clone())
+      .or_insert(Value::Null);
+  }
+  *part = value;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::filesystem::save_file_content;
+  use serde_json::json;
+  use tempdir::TempDir;
+
+  #[test]
+  fn bundle_refs_should_hoist_external_refs_in_the_same_sections() -> Result<(), anyhow::Error> {
+    let document = DocumentPath::parse("_samples/resolver/petshop_with_external.yaml")?;
+    let bundled = bundle_refs(&document, &Default::default(), &mut Default::default())?;
+
+    let schema = &bundled["paths"]["/pet"]["post"]["parameters"][0]["schema"];
+    assert_eq!(schema, &json!({ "$ref": "#/definitions/Pet" }));
+    let pet = &bundled["definitions"]["Pet"];
+    assert_eq!(pet["properties"]["category"]["$ref"], "#/definitions/Category");
+    assert_eq!(pet["properties"]["tags"]["items"]["$ref"], "#/definitions/Tag");
+    assert_eq!(
+      bundled["definitions"].as_object().map(|d| d.keys().cloned().collect::<Vec<_>>()),
+      Some(
+        ["Pet", "Category", "Tag", "ApiResponse", "Order", "User"]
+          .map(String::from)
+          .to_vec()
+      )
+    );
+    assert!(!bundled.to_string().contains("petshop_externals.yaml"));
+    Ok(())
+  }
+
+  #[test]
+  fn bundle_refs_should_name_whole_documents_after_their_file() -> Result<(), anyhow::Error> {
+    let document = DocumentPath::parse("_samples/resolver/simple1.yaml")?;
+    let bundled = bundle_refs(&document, &Default::default(), &mut Default::default())?;
+
+    assert_eq!(bundled["value"], json!({ "$ref": "#/components/schemas/simple2" }));
+    assert_eq!(
+      bundled["components"]["schemas"]["simple2"]["subvalue"],
+      json!({ "$ref": "#/components/schemas/value" })
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn refs_to_embedded_ids_should_stay_internal() -> Result<(), anyhow::Error> {
+    let tmp = TempDir::new("BUNDLE_tests")?;
+    save_file_content(
+      tmp.path(),
+      "customer.json",
+      r##"{
+        "$id": "https://example.com/schemas/customer",
+        "properties": { "address": { "$ref": "address.json" }, "street": { "$ref": "address.json#street" } },
+        "$defs": {
+          "address": { "$id": "address.json", "properties": { "street": { "$anchor": "street", "type": "string" } } }
+        }
+      }"##,
+    )?;
+    let document = DocumentPath::parse(&tmp.path().join("customer.json").to_string_lossy())?;
+    let bundled = bundle_refs(&document, &Default::default(), &mut Default::default())?;
+
+    assert_eq!(bundled["properties"]["address"], json!({ "$ref": "#/$defs/address" }));
+    assert_eq!(
+      bundled["properties"]["street"],
+      json!({ "$ref": "#/$defs/address/properties/street" })
+    );
+    assert!(bundled.get("components").is_none());
+    Ok(())
+  }
+
+  #[test]
+  fn hoisted_values_should_not_replace_existing_ones() {
+    let root = json!({ "components": { "schemas": { "Pet": {} } } });
+    let document_path = DocumentPath::FileName("api.yaml".into());
+    let mut bundle = Bundle {
+      root_doc: &document_path,
+      root: &root,
+      load_options: &Default::default(),
+      hoisted_refs: Default::default(),
+      hoisted: Default::default(),
+      taken: Default::default(),
+      ids: Default::default(),
+    };
+    let ref_info = RefInfo::parse(&document_path, "other.yaml#/components/schemas/Pet").expect("Should parse");
+    assert_eq!(bundle.hoisting_location(&ref_info), vec!["components", "schemas", "Pet2"]);
+    assert_eq!(bundle.hoisting_location(&ref_info), vec!["components", "schemas", "Pet3"]);
+  }
+}
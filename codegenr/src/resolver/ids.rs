@@ -0,0 +1,244 @@
+use super::{document_key, escape_pointer_token, PATH_SEP, SHARP_SEP};
+use crate::loaders::DocumentPath;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+const ID: &str = "$id";
+const ANCHOR: &str = "$anchor";
+
+/// A json value declared with an `$id` or an `$anchor`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdTarget {
+  pub document_path: DocumentPath,
+  /// Json pointer to the value in its document
+  pub pointer: String,
+}
+
+/// Index of the `$id` and `$anchor` declarations (JSON Schema 2020-12, OpenAPI 3.1) of the loaded documents,
+/// so `$ref`s to them are resolved to the embedded values instead of being loaded
+#[derive(Debug, Default)]
+pub struct IdsIndex {
+  indexed: HashSet<DocumentPath>,
+  /// Json pointer and base uri of the values declaring an `$id`, by document
+  resources: HashMap<DocumentPath, Vec<(String, Url)>>,
+  /// Values by absolute `$id` uri
+  ids: HashMap<String, IdTarget>,
+  /// Values by resource (its base uri, or its document when it has none) and anchor
+  anchors: HashMap<(String, String), IdTarget>,
+}
+
+impl IdsIndex {
+  /// Indexes the declarations of a document, if not already done
+  pub fn index(&mut self, document_path: &DocumentPath, json: &Value) {
+    if !self.indexed.insert(document_path.clone()) {
+      return;
+    }
+    let base = match document_path {
+      DocumentPath::Url(url) => Some(url.clone()),
+      _ => None,
+    };
+    self.index_value(document_path, json, base, "");
+  }
+
+  fn index_value(&mut self, document_path: &DocumentPath, json: &Value, base: Option<Url>, pointer: &str) {
+    match json {
+      Value::Array(a) => {
+        for (i, v) in a.iter().enumerate() {
+          self.index_value(document_path, v, base.clone(), &format!("{pointer}{PATH_SEP}{i}"));
+        }
+      }
+      Value::Object(obj) => {
+        let mut base = base;
+        if let Some(Value::String(id)) = obj.get(ID) {
+          match id.strip_prefix(SHARP_SEP) {
+            // Before 2019-09, anchors were declared as `$id: "#name"`
+            Some(anchor) => self.add_anchor(document_path, &base, anchor, pointer),
+            None => {
+              let url = match &base {
+                Some(b) => b.join(id),
+                None => Url::parse(id),
+              };
+              if let Ok(mut url) = url {
+                url.set_fragment(None);
+                let resources = self.resources.entry(document_path.clone()).or_default();
+                resources.push((pointer.into(), url.clone()));
+                self.ids.insert(url.to_string(), target(document_path, pointer));
+                base = Some(url);
+              }
+            }
+          }
+        }
+        if let Some(Value::String(anchor)) = obj.get(ANCHOR) {
+          self.add_anchor(document_path, &base, anchor, pointer);
+        }
+        for (k, v) in obj {
+          self.index_value(
+            document_path,
+            v,
+            base.clone(),
+            &format!("{pointer}{PATH_SEP}{}", escape_pointer_token(k)),
+          );
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn add_anchor(&mut self, document_path: &DocumentPath, base: &Option<Url>, anchor: &str, pointer: &str) {
+    self.anchors.insert(
+      (resource_key(document_path, base.as_ref()), anchor.into()),
+      target(document_path, pointer),
+    );
+  }
+
+  /// Json pointer and base uri of the nearest value declaring an `$id` enclosing the pointer
+  fn nearest_resource(&self, document_path: &DocumentPath, pointer: &str) -> Option<&(String, Url)> {
+    let encloses = |resource: &str| match pointer.strip_prefix(resource) {
+      Some(rest) => rest.is_empty() || rest.starts_with(PATH_SEP),
+      None => false,
+    };
+    self
+      .resources
+      .get(document_path)
+      .into_iter()
+      .flatten()
+      .filter(|(resource, _)| encloses(resource))
+      .max_by_key(|(resource, _)| resource.len())
+  }
+
+  /// Base uri of the value at the pointer: the one of its nearest enclosing `$id`, or the document url
+  fn base_at(&self, document_path: &DocumentPath, pointer: &str) -> Option<Url> {
+    match (self.nearest_resource(document_path, pointer), document_path) {
+      (Some((_, base)), _) => Some(base.clone()),
+      (None, DocumentPath::Url(url)) => Some(url.clone()),
+      (None, _) => None,
+    }
+  }
+
+  /// Finds the value a `$ref`, located at the pointer in the current document, targets through the indexed declarations.
+  /// Relative uris, and json pointer fragments, are resolved from the nearest `$id` enclosing the `$ref`.
+  pub fn lookup(&self, current_doc: &DocumentPath, pointer: &str, ref_value: &str) -> Option<IdTarget> {
+    let (uri, fragment) = match ref_value.split_once(SHARP_SEP) {
+      Some((uri, fragment)) => (uri, Some(fragment)),
+      None => (ref_value, None),
+    };
+    let base = self.base_at(current_doc, pointer);
+
+    if uri.is_empty() {
+      return match fragment {
+        Some(anchor) if !anchor.is_empty() && !anchor.starts_with(PATH_SEP) => {
+          let key = (resource_key(current_doc, base.as_ref()), anchor.to_string());
+          self.anchors.get(&key).cloned()
+        }
+        // Pointers are resolved from the document root, unless they are in an embedded resource
+        Some(fragment) => match self.nearest_resource(current_doc, pointer) {
+          Some((resource, _)) if !resource.is_empty() => Some(target(current_doc, &format!("{resource}{fragment}"))),
+          _ => None,
+        },
+        None => None,
+      };
+    }
+
+    let mut url = match base {
+      Some(base) => base.join(uri).ok()?,
+      None => Url::parse(uri).ok()?,
+    };
+    url.set_fragment(None);
+    match fragment {
+      None | Some("") => self.ids.get(url.as_str()).cloned(),
+      Some(pointer) if pointer.starts_with(PATH_SEP) => self.ids.get(url.as_str()).map(|t| IdTarget {
+        document_path: t.document_path.clone(),
+        pointer: format!("{}{pointer}", t.pointer),
+      }),
+      Some(anchor) => self.anchors.get(&(url.to_string(), anchor.to_string())).cloned(),
+    }
+  }
+}
+
+/// Key of the resource anchors are declared in
+fn resource_key(document_path: &DocumentPath, base: Option<&Url>) -> String {
+  match base {
+    Some(base) => base.to_string(),
+    None => document_key(document_path).into(),
+  }
+}
+
+fn target(document_path: &DocumentPath, pointer: &str) -> IdTarget {
+  IdTarget {
+    document_path: document_path.clone(),
+    pointer: pointer.into(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use test_case::test_case;
+
+  fn document() -> Value {
+    json!({
+      "$id": "https://example.com/schemas/customer",
+      "properties": {
+        "address": { "$ref": "address.json" }
+      },
+      "$defs": {
+        "address": {
+          "$id": "address.json",
+          "properties": {
+            "street": { "$anchor": "street", "type": "string" }
+          }
+        },
+        "name": { "$anchor": "name", "type": "string" },
+        "old": { "$id": "#old", "type": "string" }
+      }
+    })
+  }
+
+  #[test_case("address.json", Some("/$defs/address"))]
+  #[test_case("https://example.com/schemas/address.json", Some("/$defs/address"))]
+  #[test_case("https://example.com/schemas/address.json#/properties", Some("/$defs/address/properties"))]
+  #[test_case("address.json#street", Some("/$defs/address/properties/street"))]
+  #[test_case("#street", None)]
+  #[test_case("#name", Some("/$defs/name"))]
+  #[test_case("customer#name", Some("/$defs/name"))]
+  #[test_case("#old", Some("/$defs/old"))]
+  #[test_case("#/$defs/name", None)]
+  #[test_case("#unknown", None)]
+  #[test_case("other.json", None)]
+  fn lookup_tests(ref_value: &str, expected_pointer: Option<&str>) {
+    let document_path = DocumentPath::FileName("customer.yaml".into());
+    let mut index = IdsIndex::default();
+    index.index(&document_path, &document());
+    let target = index.lookup(&document_path, "/properties/address", ref_value);
+    assert_eq!(target.map(|t| t.pointer), expected_pointer.map(String::from));
+  }
+
+  #[test_case("/$defs/address/properties/street", "#street", Some("/$defs/address/properties/street"))]
+  #[test_case("/$defs/address/properties/street", "#name", None)]
+  #[test_case("/$defs/address/properties/street", "customer#name", Some("/$defs/name"))]
+  #[test_case("/$defs/geo/properties/zip", "zip.json", Some("/$defs/geo/$defs/zip"))]
+  #[test_case("/$defs/geo/properties/zip", "zip.json#code", Some("/$defs/geo/$defs/zip/properties/code"))]
+  #[test_case("/$defs/geo/properties/zip", "#/properties", Some("/$defs/geo/properties"))]
+  #[test_case("/$defs/geo/properties/zip", "#/$defs/zip", Some("/$defs/geo/$defs/zip"))]
+  #[test_case("/$defs/geo/properties/zip", "#", Some("/$defs/geo"))]
+  #[test_case("/properties/address", "#/$defs/geo", None)]
+  #[test_case("/$defs/geography", "zip.json", None)]
+  #[test_case("/properties/address", "geo/zip.json", Some("/$defs/geo/$defs/zip"))]
+  fn nested_ids_lookup_tests(pointer: &str, ref_value: &str, expected_pointer: Option<&str>) {
+    let mut document = document();
+    document["$defs"]["geo"] = json!({
+      "$id": "geo/location.json",
+      "properties": { "zip": { "$ref": "zip.json" } },
+      "$defs": {
+        "zip": { "$id": "zip.json", "properties": { "code": { "$anchor": "code", "type": "string" } } }
+      }
+    });
+    let document_path = DocumentPath::FileName("customer.yaml".into());
+    let mut index = IdsIndex::default();
+    index.index(&document_path, &document);
+    let target = index.lookup(&document_path, pointer, ref_value);
+    assert_eq!(target.map(|t| t.pointer), expected_pointer.map(String::from));
+  }
+}
@@ -0,0 +1,1168 @@
+mod bundle;
+mod ids;
+
+use crate::{
+  loaders::{DocumentPath, LoadOptions, LoaderError},
+  OriginalDocumentsHash, ResolvedDocumentsHash,
+};
+pub use bundle::bundle_refs;
+use ids::IdsIndex;
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::HashSet, rc::Rc, str::FromStr};
+use thiserror::Error;
+use tracing::warn;
+
+const REF: &str = "$ref";
+const PATH_SEP: char = '/';
+const SHARP_SEP: char = '#';
+const FROM_REF: &str = "x-fromRef";
+const REF_NAME: &str = "x-refName";
+const RECURSIVE: &str = "x-recursive";
+
+#[derive(Error, Debug)]
+pub enum ResolverError {
+  #[error("Loading error: `{0}`.")]
+  Loading(#[from] LoaderError),
+  #[error("`$ref` value should be a String.")]
+  ShouldBeString,
+  #[error("Key `{key}` was not found at `{pointer}` in json part `{part}`.")]
+  KeyNotFound { key: String, pointer: String, part: Value },
+  #[error("Index `{index}` was not found at `{pointer}` in an array of {len} items.")]
+  IndexNotFound { index: String, pointer: String, len: usize },
+  #[error("Could not follow pointer `{0}` as json part is neither an object nor an array.")]
+  NotAContainer(String),
+  #[error("Pointer `{0}` is not a valid percent-encoded utf8 string.")]
+  InvalidPointer(String),
+  #[error("`$ref` value `{0}` parse error. There should be no more than 2 parts separated by # in a reference path.")]
+  NoMoreThanTwoParts(String),
+  #[error("Circular `$ref` detected: `{}`.", .0.join("` -> `"))]
+  CircularRef(Vec<String>),
+  #[error("`{0}` is not a circular refs policy. It should be `error`, `placeholder`, `depth=<N>` or `link`.")]
+  UnknownCircularRefs(String),
+}
+
+/// What to do with a `$ref` met while it is already being resolved
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircularRefs {
+  /// Fails with a `CircularRef` error listing the chain of refs
+  #[default]
+  Error,
+  /// Leaves the circular `$ref` as is
+  Placeholder,
+  /// Follows a circular `$ref` until it appears N times in the chain of refs, then leaves it as is
+  Depth(usize),
+  /// Resolves each referenced value once, where it is first met, and replaces the circular `$ref`s
+  /// and the later `$ref`s to it with a `{ "x-refName": .., "x-fromRef": .., "x-recursive": true }` marker
+  Link,
+}
+
+impl FromStr for CircularRefs {
+  type Err = ResolverError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.split_once('=') {
+      None if s == "error" => Ok(CircularRefs::Error),
+      None if s == "placeholder" => Ok(CircularRefs::Placeholder),
+      None if s == "link" => Ok(CircularRefs::Link),
+      Some(("depth", depth)) => depth
+        .trim()
+        .parse()
+        .map(CircularRefs::Depth)
+        .map_err(|_| ResolverError::UnknownCircularRefs(s.into())),
+      _ => Err(ResolverError::UnknownCircularRefs(s.into())),
+    }
+  }
+}
+
+/// How the `$ref`s of a document are resolved
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct ResolverOptions {
+  pub circular_refs: CircularRefs,
+  /// Key under which a `$ref`ed value that is not an object is kept, when the `$ref` has sibling keys.
+  /// If not set, the sibling keys are dropped and the value replaces the whole `$ref` object.
+  pub ref_value_key: Option<String>,
+  /// How the remote documents are downloaded
+  pub load: LoadOptions,
+}
+
+/// State of a document resolution
+struct Resolution {
+  options: ResolverOptions,
+  /// Ids of the `$ref`s being resolved, from the outermost one
+  chain: Vec<String>,
+  /// Ids of the already resolved `$ref`s, when each one is resolved once
+  linked: HashSet<String>,
+  /// `$id` and `$anchor` declarations of the documents met so far
+  ids: IdsIndex,
+}
+
+impl Resolution {
+  fn new(options: ResolverOptions) -> Self {
+    Self {
+      options,
+      chain: Default::default(),
+      linked: Default::default(),
+      ids: Default::default(),
+    }
+  }
+
+  /// Tells if a `$ref` should be left as is, because it is circular
+  fn should_cut(&self, ref_id: &str) -> Result<bool, ResolverError> {
+    let occurrences = self.chain.iter().filter(|id| *id == ref_id).count();
+    match self.options.circular_refs {
+      _ if occurrences == 0 => Ok(false),
+      CircularRefs::Error => {
+        let start = self.chain.iter().position(|id| id == ref_id).unwrap_or_default();
+        let mut cycle = self.chain[start..].to_vec();
+        cycle.push(ref_id.into());
+        Err(ResolverError::CircularRef(cycle))
+      }
+      CircularRefs::Placeholder | CircularRefs::Link => Ok(true),
+      CircularRefs::Depth(depth) => Ok(occurrences >= depth),
+    }
+  }
+}
+
+enum Json {
+  Original(Rc<Value>),
+  Resolved(Rc<Value>),
+}
+
+fn ensure_orignal_json(
+  doc_path: &DocumentPath,
+  load_options: &LoadOptions,
+  original_cache: &mut OriginalDocumentsHash,
+) -> Result<Rc<Value>, ResolverError> {
+  use std::collections::hash_map::Entry::*;
+  match original_cache.entry(doc_path.clone()) {
+    Occupied(entry) => Ok(entry.get().clone()),
+    Vacant(entry) => {
+      let json = doc_path.load_raw_with(load_options)?;
+      let rc = Rc::new(json);
+      entry.insert(rc.clone());
+      Ok(rc)
+    }
+  }
+}
+
+fn get_resolved_json(doc_path: &DocumentPath, options: &ResolverOptions, resolved_cache: &mut ResolvedDocumentsHash) -> Option<Rc<Value>> {
+  resolved_cache.get(&(doc_path.clone(), options.clone())).cloned()
+}
+
+fn get_resolved_or_original(
+  doc_path: &DocumentPath,
+  options: &ResolverOptions,
+  original_cache: &mut OriginalDocumentsHash,
+  resolved_cache: &mut ResolvedDocumentsHash,
+) -> Result<Json, ResolverError> {
+  match get_resolved_json(doc_path, options, resolved_cache) {
+    Some(json) => Ok(Json::Resolved(json)),
+    None => ensure_orignal_json(doc_path, &options.load, original_cache).map(Json::Original),
+  }
+}
+
+#[::tracing::instrument(level = "trace")]
+pub fn resolve_refs_raw(json: Value) -> Result<Value, ResolverError> {
+  resolve_refs_raw_with(json, Default::default())
+}
+
+fn resolve_refs_raw_with(json: Value, options: ResolverOptions) -> Result<Value, ResolverError> {
+  let mut resolving = json.clone();
+  let mut resolution = Resolution::new(options);
+  resolution.ids.index(&DocumentPath::None, &json);
+  resolve_refs_recurse(
+    &DocumentPath::None,
+    &mut resolving,
+    "",
+    &json,
+    &mut resolution,
+    &mut Default::default(),
+    &mut Default::default(),
+  )?;
+  Ok(resolving)
+}
+
+#[::tracing::instrument(level = "trace")]
+pub fn resolve_refs(
+  document: DocumentPath,
+  options: &ResolverOptions,
+  original_cache: &mut OriginalDocumentsHash,
+  resolved_cache: &mut ResolvedDocumentsHash,
+) -> Result<Rc<Value>, ResolverError> {
+  let json = get_resolved_or_original(&document, options, original_cache, resolved_cache)?;
+  match json {
+    Json::Resolved(j) => Ok(j),
+    Json::Original(j) => {
+      let mut resolving = (*j).clone();
+      let mut resolution = Resolution::new(options.clone());
+      resolution.ids.index(&document, &j);
+      resolve_refs_recurse(&document, &mut resolving, "", &j, &mut resolution, original_cache, resolved_cache)?;
+      let resolved = Rc::new(resolving);
+      resolved_cache.insert((document, options.clone()), resolved.clone());
+      Ok(resolved)
+    }
+  }
+}
+
+/// Lists the given document and all the documents it references through `$ref`s, recursively.
+/// Documents that could not be loaded are still listed, but not explored.
+pub fn referenced_documents(
+  document: &DocumentPath,
+  load_options: &LoadOptions,
+  original_cache: &mut OriginalDocumentsHash,
+) -> Vec<DocumentPath> {
+  let mut documents = vec![document.clone()];
+  let mut ids = IdsIndex::default();
+  let mut index = 0;
+  while let Some(doc_path) = documents.get(index).cloned() {
+    index += 1;
+    if doc_path == DocumentPath::None {
+      continue;
+    }
+    if let Ok(json) = ensure_orignal_json(&doc_path, load_options, original_cache) {
+      ids.index(&doc_path, &json);
+      collect_referenced_documents(&doc_path, &json, "", &ids, &mut documents);
+    }
+  }
+  documents
+}
+
+fn collect_referenced_documents(
+  current_doc: &DocumentPath,
+  json: &Value,
+  pointer: &str,
+  ids: &IdsIndex,
+  documents: &mut Vec<DocumentPath>,
+) {
+  match json {
+    Value::Array(a) => {
+      for (i, v) in a.iter().enumerate() {
+        collect_referenced_documents(current_doc, v, &format!("{pointer}{PATH_SEP}{i}"), ids, documents);
+      }
+    }
+    Value::Object(obj) => {
+      if let Some(Value::String(ref_value)) = obj.get(REF) {
+        let document_path = match ids.lookup(current_doc, pointer, ref_value) {
+          Some(target) => Some(target.document_path),
+          None => RefInfo::parse(current_doc, ref_value).ok().map(|r| r.document_path),
+        };
+        if let Some(document_path) = document_path {
+          if !documents.contains(&document_path) {
+            documents.push(document_path);
+          }
+        }
+      }
+      for (k, v) in obj {
+        let pointer = format!("{pointer}{PATH_SEP}{}", escape_pointer_token(k));
+        collect_referenced_documents(current_doc, v, &pointer, ids, documents);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Resolves the `$ref`s of a json value, located at the pointer in the current document
+fn resolve_refs_recurse(
+  current_doc: &DocumentPath,
+  json: &mut Value,
+  pointer: &str,
+  original: &Value,
+  resolution: &mut Resolution,
+  original_cache: &mut OriginalDocumentsHash,
+  resolved_cache: &mut ResolvedDocumentsHash,
+) -> Result<(), ResolverError> {
+  match json {
+    Value::Array(a) => {
+      for (i, v) in a.iter_mut().enumerate() {
+        let pointer = format!("{pointer}{PATH_SEP}{i}");
+        resolve_refs_recurse(current_doc, v, &pointer, original, resolution, original_cache, resolved_cache)?;
+      }
+      Ok(())
+    }
+    Value::Object(obj) => {
+      let ref_value = obj.remove(REF);
+
+      // The values merged from the `$ref` are already resolved, so they are merged after resolving the other ones
+      for (key, value) in obj.iter_mut() {
+        let pointer = format!("{pointer}{PATH_SEP}{}", escape_pointer_token(key));
+        resolve_refs_recurse(current_doc, value, &pointer, original, resolution, original_cache, resolved_cache)?;
+      }
+
+      match ref_value {
+        Some(Value::String(ref_value)) => {
+          let ref_info = match resolution.ids.lookup(current_doc, pointer, &ref_value) {
+            Some(target) => RefInfo::from_target(current_doc, target),
+            None => RefInfo::parse(current_doc, &ref_value)?,
+          };
+          let ref_id = ref_info.id();
+          let ref_name = ref_info.path.as_deref().map(get_ref_name).unwrap_or_default();
+          let is_linked = resolution.linked.contains(&ref_id);
+          if resolution.should_cut(&ref_id)? || is_linked {
+            if resolution.options.circular_refs == CircularRefs::Link {
+              obj.insert(REF_NAME.into(), Value::String(ref_name));
+              obj.insert(FROM_REF.into(), Value::String(ref_value));
+              obj.insert(RECURSIVE.into(), Value::Bool(true));
+            } else {
+              obj.insert(REF.into(), Value::String(ref_value));
+            }
+            return Ok(());
+          }
+
+          let is_nested = ref_info.document_path == *current_doc;
+          let target_pointer = ref_info.pointer();
+
+          if resolution.options.circular_refs == CircularRefs::Link {
+            resolution.linked.insert(ref_id.clone());
+          }
+          resolution.chain.push(ref_id);
+          let new_value = if is_nested {
+            let mut v = fetch_reference_value(original, &ref_info.path)?;
+            resolve_refs_recurse(
+              current_doc,
+              &mut v,
+              &target_pointer,
+              original,
+              resolution,
+              original_cache,
+              resolved_cache,
+            )?;
+            v
+          } else {
+            let doc_path = ref_info.document_path;
+            let json = get_resolved_or_original(&doc_path, &resolution.options, original_cache, resolved_cache)?;
+            match json {
+              Json::Resolved(j) => {
+                resolution.ids.index(&doc_path, &j);
+                fetch_reference_value(&j, &ref_info.path)?
+              }
+              Json::Original(j) => {
+                resolution.ids.index(&doc_path, &j);
+                let mut v = fetch_reference_value(&j, &ref_info.path)?;
+                resolve_refs_recurse(&doc_path, &mut v, &target_pointer, &j, resolution, original_cache, resolved_cache)?;
+                v
+              }
+            }
+          };
+          resolution.chain.pop();
+
+          match (new_value, &resolution.options.ref_value_key) {
+            (Value::Object(m), _) => {
+              for (k, v) in m {
+                obj.insert(k, v);
+              }
+            }
+            (value, _) if obj.is_empty() => {
+              *json = value;
+              return Ok(());
+            }
+            (value, Some(ref_value_key)) => {
+              obj.insert(ref_value_key.clone(), value);
+            }
+            (value, None) => {
+              warn!(
+                "`{}` `$ref` value is not an object, so it replaces the whole object and its `{}` keys are dropped.",
+                ref_value,
+                obj.keys().cloned().collect::<Vec<_>>().join("`, `")
+              );
+              *json = value;
+              return Ok(());
+            }
+          }
+          obj.insert(FROM_REF.into(), Value::String(ref_value));
+          obj.insert(REF_NAME.into(), Value::String(ref_name));
+        }
+        Some(_) => return Err(ResolverError::ShouldBeString),
+        None => {}
+      }
+      Ok(())
+    }
+    _ => Ok(()),
+  }
+}
+
+fn get_ref_name(path: &str) -> String {
+  let name = path.split(PATH_SEP).next_back().unwrap_or_default();
+  let name = percent_decode_str(name).decode_utf8_lossy();
+  unescape_pointer_token(&name)
+}
+
+/// Decodes the `~1` and `~0` escaped characters of a json pointer token (RFC 6901)
+pub(crate) fn unescape_pointer_token(token: &str) -> String {
+  token.replace("~1", "/").replace("~0", "~")
+}
+
+/// Escapes a json key to be a json pointer token in a, percent-encoded, url fragment
+fn escape_pointer_token(key: &str) -> String {
+  key.replace('~', "~0").replace(PATH_SEP, "~1").replace('%', "%25")
+}
+
+/// Follows a json pointer (RFC 6901) given as a, possibly percent-encoded, url fragment.
+/// A pointer not starting with `/` is considered relative to the document root.
+fn fetch_reference_value(json: &Value, path: &Option<String>) -> Result<Value, ResolverError> {
+  match path {
+    Some(p) => {
+      let pointer = percent_decode_str(p)
+        .decode_utf8()
+        .map_err(|_| ResolverError::InvalidPointer(p.clone()))?;
+      let pointer = if pointer.starts_with(PATH_SEP) || pointer.is_empty() {
+        pointer.into_owned()
+      } else {
+        format!("{PATH_SEP}{pointer}")
+      };
+
+      let mut part = json;
+      let mut prefix = String::with_capacity(pointer.len());
+      for token in pointer.split(PATH_SEP).skip(1) {
+        prefix.push(PATH_SEP);
+        prefix.push_str(token);
+        let key = unescape_pointer_token(token);
+        part = match part {
+          Value::Object(o) => o.get(&key).ok_or_else(|| ResolverError::KeyNotFound {
+            key,
+            pointer: prefix.clone(),
+            part: part.clone(),
+          })?,
+          Value::Array(a) => parse_array_index(&key)
+            .and_then(|i| a.get(i))
+            .ok_or_else(|| ResolverError::IndexNotFound {
+              index: key,
+              pointer: prefix.clone(),
+              len: a.len(),
+            })?,
+          _ => return Err(ResolverError::NotAContainer(prefix)),
+        };
+      }
+      Ok(part.clone())
+    }
+    None => Ok(json.clone()),
+  }
+}
+
+/// Array indexes are made of digits, without leading zeros
+fn parse_array_index(token: &str) -> Option<usize> {
+  if (token.starts_with('0') && token != "0") || !token.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+  token.parse().ok()
+}
+
+/// Document part of the `$ref` ids
+fn document_key(document_path: &DocumentPath) -> &str {
+  match document_path {
+    DocumentPath::Url(url) => url.as_str(),
+    DocumentPath::FileName(file_name) => file_name,
+    DocumentPath::None => "",
+  }
+}
+
+#[derive(Debug)]
+pub struct RefInfo {
+  /// Path of the reference to import in the destination file
+  pub path: Option<String>,
+  /// True if the reference is nested in the same document
+  pub is_nested: bool,
+  /// File path of the document containing the reference
+  pub document_path: DocumentPath,
+  /// Last part of the $ref value
+  pub ref_friendly_name: Option<String>,
+}
+
+impl RefInfo {
+  /// Unique identifier of the referenced value: the document path and the path in it
+  pub fn id(&self) -> String {
+    format!(
+      "{}{}{}",
+      document_key(&self.document_path),
+      SHARP_SEP,
+      self.path.as_deref().unwrap_or_default()
+    )
+  }
+
+  /// Json pointer to the referenced value in its document, as the `$ref` wrote it
+  fn pointer(&self) -> String {
+    match self.path.as_deref() {
+      Some(path) if !path.is_empty() && !path.starts_with(PATH_SEP) => format!("{PATH_SEP}{path}"),
+      path => path.unwrap_or_default().into(),
+    }
+  }
+
+  /// Reference to a value found through its `$id` or `$anchor` declaration
+  fn from_target(doc_path: &DocumentPath, target: ids::IdTarget) -> Self {
+    Self {
+      is_nested: doc_path == &target.document_path,
+      ref_friendly_name: Some(get_ref_name(&target.pointer)),
+      path: Some(target.pointer),
+      document_path: target.document_path,
+    }
+  }
+
+  pub fn parse(doc_path: &DocumentPath, ref_value: &str) -> Result<Self, ResolverError> {
+    let mut parts = ref_value.split(SHARP_SEP);
+
+    let (ref_doc_path, path) = match (parts.next(), parts.next(), parts.next()) {
+      (_, _, Some(_)) => return Err(ResolverError::NoMoreThanTwoParts(ref_value.into())),
+      (Some(file), None, None) => (DocumentPath::parse(file)?.relate_from(doc_path)?, None),
+      (Some(""), Some(p), None) => (doc_path.clone(), Some(p.to_string())),
+      (Some(file), Some(p), None) => (DocumentPath::parse(file)?.relate_from(doc_path)?, Some(p.to_string())),
+      (None, _, _) => unreachable!("Split always returns at least one element"),
+    };
+
+    let is_nested: bool = doc_path == &ref_doc_path;
+    let ref_friendly_name = path.as_deref().map(get_ref_name);
+
+    Ok(Self {
+      path,
+      is_nested,
+      document_path: ref_doc_path,
+      ref_friendly_name,
+    })
+  }
+}
+
+/// Resolves a sample document with the default options, for the tests
+#[cfg(test)]
+pub(crate) fn resolve_sample(path: &str) -> Result<Rc<Value>, anyhow::Error> {
+  let document = DocumentPath::parse(path)?;
+  Ok(resolve_refs(
+    document,
+    &Default::default(),
+    &mut Default::default(),
+    &mut Default::default(),
+  )?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::loaders::*;
+  use serde_json::json;
+  use test_case::test_case;
+
+  #[test]
+  fn resolve_reference_test() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "test": {
+        "data1": {
+          "value": 42
+        },
+        "data2": [
+          1,2,3
+        ]
+      },
+      "myref": {
+        "data": "test"
+      }
+    });
+
+    assert_eq!(
+      fetch_reference_value(&json, &Some("/test/data1/value".into()))?,
+      Value::Number(serde_json::Number::from(42))
+    );
+
+    assert_eq!(fetch_reference_value(&json, &Some("/test/data1".into()))?, json!({ "value": 42 }));
+
+    let path: &str = "/test/not_existing_path";
+    let failed_test = fetch_reference_value(&json, &Some(path.into()));
+    let err = failed_test.expect_err("Should be an error");
+    assert_eq!(
+      err.to_string(),
+      "Key `not_existing_path` was not found at `/test/not_existing_path` in json part `{\"data1\":{\"value\":42},\"data2\":[1,2,3]}`."
+    );
+
+    Ok(())
+  }
+
+  #[test_case("/test/data2/1", json!(2))]
+  #[test_case("/test/data2/0", json!(1))]
+  #[test_case("/paths/~1users~1{id}/get", json!({ "id": "getUser" }))]
+  #[test_case("/paths/~1users~1%7Bid%7D/get", json!({ "id": "getUser" }))]
+  #[test_case("/tilde~0key", json!("tilde"))]
+  #[test_case("/with%20space", json!("space"))]
+  #[test_case("/", json!("empty key"))]
+  #[test_case("", json!("whole document"))]
+  fn fetch_reference_value_json_pointer_tests(pointer: &str, expected: Value) -> Result<(), anyhow::Error> {
+    let json = json!({
+      "test": { "data2": [1, 2, 3] },
+      "paths": { "/users/{id}": { "get": { "id": "getUser" } } },
+      "tilde~key": "tilde",
+      "with space": "space",
+      "": "empty key"
+    });
+    let expected = if pointer.is_empty() { json.clone() } else { expected };
+    assert_eq!(fetch_reference_value(&json, &Some(pointer.into()))?, expected);
+    Ok(())
+  }
+
+  #[test_case("/data/3", "Index `3` was not found at `/data/3` in an array of 3 items.")]
+  #[test_case("/data/01", "Index `01` was not found at `/data/01` in an array of 3 items.")]
+  #[test_case("/data/-", "Index `-` was not found at `/data/-` in an array of 3 items.")]
+  #[test_case(
+    "/data/0/value",
+    "Could not follow pointer `/data/0/value` as json part is neither an object nor an array."
+  )]
+  #[test_case("/data%FF", "Pointer `/data%FF` is not a valid percent-encoded utf8 string.")]
+  fn fetch_reference_value_json_pointer_errors_tests(pointer: &str, expected: &str) {
+    let json = json!({ "data": [1, 2, 3] });
+    let err = fetch_reference_value(&json, &Some(pointer.into())).expect_err("Should be an error");
+    assert_eq!(err.to_string(), expected);
+  }
+
+  #[test]
+  fn resolve_refs_should_follow_escaped_and_array_pointers() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "paths": { "/users/{id}": { "get": { "operationId": "getUser" } } },
+      "schemas": { "allOf": [ { "type": "object" } ] },
+      "first": { "$ref": "#/paths/~1users~1%7Bid%7D/get" },
+      "second": { "$ref": "#/schemas/allOf/0" }
+    });
+    let resolved = resolve_refs_raw(json)?;
+    assert_eq!(resolved["first"]["operationId"], "getUser");
+    assert_eq!(resolved["first"]["x-refName"], "get");
+    assert_eq!(resolved["second"]["type"], "object");
+    assert_eq!(resolved["second"]["x-refName"], "0");
+    Ok(())
+  }
+
+  #[test]
+  fn resolve_refs_test_0() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "test": {
+        "$ref": "#/myref"
+      },
+      "myref": {
+        "data": "test"
+      }
+    });
+
+    let expected = json!({
+      "test": {
+        "data": "test",
+        "x-fromRef": "#/myref",
+        "x-refName": "myref",
+      },
+      "myref": {
+        "data": "test"
+      }
+    });
+
+    let resolved = resolve_refs_raw(json)?;
+    println!("{}", resolved);
+    println!("{}", expected);
+    assert_eq!(resolved, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn resolve_refs_test_1() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "test": {
+        "$ref": "#myref"
+      },
+      "myref": {
+        "data": "test"
+      }
+    });
+
+    let expected = json!({
+      "test": {
+        "data": "test",
+        "x-fromRef": "#myref",
+        "x-refName": "myref",
+      },
+      "myref": {
+        "data": "test"
+      }
+    });
+
+    let resolved = resolve_refs_raw(json)?;
+    println!("{}", resolved);
+    println!("{}", expected);
+    assert_eq!(resolved, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn resolve_refs_test_2() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "test": {
+        "data1": {
+          "$ref": "#/myref"
+        },
+        "data2": {
+          "$ref": "#/myref"
+        }
+      },
+      "myref": {
+        "data": "test"
+      }
+    });
+
+    let expected = json!({
+      "test": {
+        "data1": {
+          "data": "test",
+          "x-fromRef": "#/myref",
+          "x-refName": "myref"
+        },
+        "data2": {
+          "data": "test",
+          "x-fromRef": "#/myref",
+          "x-refName": "myref"
+        }
+      },
+      "myref": {
+        "data": "test"
+      }
+    });
+
+    let resolved = resolve_refs_raw(json)?;
+    println!("{}", resolved);
+    println!("{}", expected);
+    assert_eq!(resolved, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn resolve_refs_test_3() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "test": {
+        "data1": {
+          "$ref": "#/myref"
+        },
+        "data2": {
+          "$ref": "#/myref"
+        }
+      },
+      "myref": {
+        "data": {
+          "$ref": "#/myref2"
+        }
+      },
+      "myref2": {
+        "content": {
+          "data": "test"
+        }
+      }
+    });
+
+    let expected = json!({
+      "test": {
+        "data1": {
+          "data": {
+            "content": {
+              "data": "test"
+            },
+            "x-fromRef": "#/myref2",
+            "x-refName": "myref2"
+          },
+          "x-fromRef": "#/myref",
+          "x-refName": "myref"
+        },
+        "data2": {
+          "data": {
+            "content": {
+              "data": "test"
+            },
+            "x-fromRef": "#/myref2",
+            "x-refName": "myref2"
+          },
+          "x-fromRef": "#/myref",
+          "x-refName": "myref"
+        }
+      },
+      "myref": {
+        "data": {
+          "content": {
+            "data": "test"
+          },
+          "x-fromRef": "#/myref2",
+          "x-refName": "myref2"
+        }
+      },
+      "myref2": {
+        "content": {
+          "data": "test"
+        }
+      }
+    });
+
+    let resolved = resolve_refs_raw(json)?;
+    println!("{}", resolved);
+    println!("{}", expected);
+    assert_eq!(resolved, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn resolve_refs_test_4() -> Result<(), anyhow::Error> {
+    let json = json!({
+        "test": {
+          "data1": {
+            "$ref": "#/myref"
+          },
+          "data2": {
+            "$ref": "#/myref"
+          }
+        },
+        "myref": {
+          "data": {
+            "$ref": "#/myref2"
+          }
+        },
+        "myref2": {
+          "content": {
+            "data": "test"
+          }
+        }
+    });
+
+    let expected = json!({
+       "test": {
+          "data1": {
+             "data": {
+                "content": {
+                   "data": "test"
+                },
+                "x-fromRef": "#/myref2",
+                "x-refName": "myref2"
+             },
+             "x-fromRef": "#/myref",
+             "x-refName": "myref"
+          },
+          "data2": {
+             "data": {
+                "content": {
+                   "data": "test"
+                },
+                "x-fromRef": "#/myref2",
+                "x-refName": "myref2"
+             },
+             "x-fromRef": "#/myref",
+             "x-refName": "myref"
+          }
+       },
+       "myref": {
+          "data": {
+             "content": {
+                "data": "test"
+             },
+             "x-fromRef": "#/myref2",
+             "x-refName": "myref2"
+          }
+       },
+       "myref2": {
+          "content": {
+             "data": "test"
+          }
+       }
+    });
+
+    let resolved = resolve_refs_raw(json)?;
+    println!("{}", resolved);
+    println!("{}", expected);
+    assert_eq!(resolved, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn should_resolve_nested_references() -> Result<(), anyhow::Error> {
+    let json = DocumentPath::parse("_samples/resolver/petshop.yaml")?.load_raw()?;
+    let json = resolve_refs_raw(json)?;
+    let string = json.to_string();
+    assert!(!string.contains(REF));
+    Ok(())
+  }
+
+  #[test]
+  fn should_resolve_external_references() -> Result<(), anyhow::Error> {
+    let document = DocumentPath::parse("_samples/resolver/petshop_with_external.yaml")?;
+    let json = resolve_refs(document, &Default::default(), &mut Default::default(), &mut Default::default())?;
+    let string = json.to_string();
+    assert!(!string.contains(REF));
+    Ok(())
+  }
+
+  fn circular(circular_refs: CircularRefs) -> ResolverOptions {
+    ResolverOptions {
+      circular_refs,
+      ..Default::default()
+    }
+  }
+
+  fn tree() -> Value {
+    json!({
+      "Node": {
+        "type": "object",
+        "properties": {
+          "children": { "type": "array", "items": { "$ref": "#/Node" } }
+        }
+      }
+    })
+  }
+
+  #[test]
+  fn circular_refs_should_fail_with_the_refs_chain() {
+    let err = resolve_refs_raw(tree()).expect_err("Should be an error");
+    assert!(matches!(&err, ResolverError::CircularRef(chain) if chain == &vec!["#/Node".to_string(), "#/Node".to_string()]));
+    assert_eq!(err.to_string(), "Circular `$ref` detected: `#/Node` -> `#/Node`.");
+  }
+
+  #[test]
+  fn circular_refs_should_report_the_whole_cycle() {
+    let json = json!({
+      "A": { "b": { "$ref": "#/B" } },
+      "B": { "c": { "$ref": "#/C" } },
+      "C": { "a": { "$ref": "#/A" } }
+    });
+    let err = resolve_refs_raw(json).expect_err("Should be an error");
+    assert_eq!(err.to_string(), "Circular `$ref` detected: `#/B` -> `#/C` -> `#/A` -> `#/B`.");
+  }
+
+  #[test]
+  fn circular_refs_should_be_left_as_placeholders() -> Result<(), anyhow::Error> {
+    let resolved = resolve_refs_raw_with(tree(), circular(CircularRefs::Placeholder))?;
+    let level1 = &resolved["Node"]["properties"]["children"]["items"];
+    assert_eq!(level1["x-refName"], "Node");
+    assert_eq!(level1["properties"]["children"]["items"], json!({ "$ref": "#/Node" }));
+    Ok(())
+  }
+
+  #[test]
+  fn circular_refs_should_be_cut_at_depth() -> Result<(), anyhow::Error> {
+    let resolved = resolve_refs_raw_with(tree(), circular(CircularRefs::Depth(2)))?;
+    let items = |v: &Value| v["properties"]["children"]["items"].clone();
+    let level1 = items(&resolved["Node"]);
+    let level2 = items(&level1);
+    let level3 = items(&level2);
+    assert_eq!(level2["x-refName"], "Node");
+    assert_eq!(level3, json!({ "$ref": "#/Node" }));
+    Ok(())
+  }
+
+  #[test]
+  fn circular_refs_should_be_linked() -> Result<(), anyhow::Error> {
+    let mut json = tree();
+    json["Forest"] = json!({ "trees": { "type": "array", "items": { "$ref": "#/Node" } } });
+    let resolved = resolve_refs_raw_with(json, circular(CircularRefs::Link))?;
+    let node = &resolved["Node"]["properties"]["children"]["items"];
+    assert_eq!(node["x-refName"], "Node");
+    assert_eq!(
+      node["properties"]["children"]["items"],
+      json!({ "x-refName": "Node", "x-fromRef": "#/Node", "x-recursive": true })
+    );
+    assert_eq!(
+      resolved["Forest"]["trees"]["items"],
+      json!({ "x-refName": "Node", "x-fromRef": "#/Node", "x-recursive": true })
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn shared_refs_should_be_linked_after_the_first_one() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "Pet": { "type": "object", "properties": { "name": { "type": "string" } } },
+      "Owner": {
+        "properties": {
+          "pet": { "$ref": "#/Pet" },
+          "pets": { "type": "array", "items": { "$ref": "#/Pet" } }
+        }
+      }
+    });
+    let resolved = resolve_refs_raw_with(json, circular(CircularRefs::Link))?;
+    let properties = &resolved["Owner"]["properties"];
+    assert_eq!(properties["pet"]["x-refName"], "Pet");
+    assert_eq!(properties["pet"]["properties"]["name"]["type"], "string");
+    assert_eq!(
+      properties["pets"]["items"],
+      json!({ "x-refName": "Pet", "x-fromRef": "#/Pet", "x-recursive": true })
+    );
+    Ok(())
+  }
+
+  fn shared_values() -> Value {
+    json!({
+      "enums": { "status": ["on", "off"] },
+      "example": 42,
+      "status": { "$ref": "#/enums/status" },
+      "described": { "$ref": "#/example", "description": "The answer" }
+    })
+  }
+
+  #[test]
+  fn refs_should_be_resolved_through_ids_and_anchors() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "$id": "https://example.com/schemas/customer",
+      "properties": {
+        "address": { "$ref": "address.json" },
+        "street": { "$ref": "https://example.com/schemas/address.json#/properties/street" },
+        "name": { "$ref": "#name" }
+      },
+      "$defs": {
+        "address": {
+          "$id": "address.json",
+          "properties": { "street": { "type": "string" } }
+        },
+        "name": { "$anchor": "name", "type": "string" }
+      }
+    });
+    let resolved = resolve_refs_raw(json)?;
+    let properties = &resolved["properties"];
+    assert_eq!(properties["address"]["$id"], "address.json");
+    assert_eq!(properties["address"]["x-refName"], "address");
+    assert_eq!(properties["street"]["type"], "string");
+    assert_eq!(properties["name"]["type"], "string");
+    assert_eq!(properties["name"]["x-fromRef"], "#name");
+    Ok(())
+  }
+
+  #[test]
+  fn relative_refs_should_be_resolved_from_the_nearest_id() -> Result<(), anyhow::Error> {
+    let json = json!({
+      "$id": "https://example.com/schemas/customer",
+      "properties": { "zip": { "$ref": "zip.json" }, "local": { "$ref": "#/$defs/zip" } },
+      "$defs": {
+        "zip": { "$id": "zip.json", "type": "integer" },
+        "code": { "$anchor": "code", "type": "integer" },
+        "geo": {
+          "$id": "geo/location.json",
+          "properties": { "zip": { "$ref": "zip.json" }, "code": { "$ref": "#code" }, "local": { "$ref": "#/$defs/zip" } },
+          "$defs": {
+            "zip": { "$id": "zip.json", "type": "string" },
+            "code": { "$anchor": "code", "type": "string" }
+          }
+        }
+      }
+    });
+    let resolved = resolve_refs_raw(json)?;
+    assert_eq!(resolved["properties"]["zip"]["type"], "integer");
+    assert_eq!(resolved["properties"]["local"]["type"], "integer");
+    let geo = &resolved["$defs"]["geo"]["properties"];
+    assert_eq!(geo["zip"]["type"], "string");
+    assert_eq!(geo["code"]["type"], "string");
+    assert_eq!(geo["local"]["type"], "string");
+    Ok(())
+  }
+
+  #[test]
+  fn non_object_refs_should_replace_the_ref_object() -> Result<(), anyhow::Error> {
+    let resolved = resolve_refs_raw(shared_values())?;
+    assert_eq!(resolved["status"], json!(["on", "off"]));
+    assert_eq!(resolved["described"], json!(42));
+    Ok(())
+  }
+
+  #[test]
+  fn non_object_refs_should_be_wrapped_when_having_siblings() -> Result<(), anyhow::Error> {
+    let options = ResolverOptions {
+      ref_value_key: Some("x-value".into()),
+      ..Default::default()
+    };
+    let resolved = resolve_refs_raw_with(shared_values(), options)?;
+    assert_eq!(resolved["status"], json!(["on", "off"]));
+    assert_eq!(
+      resolved["described"],
+      json!({ "description": "The answer", "x-value": 42, "x-fromRef": "#/example", "x-refName": "example" })
+    );
+    Ok(())
+  }
+
+  #[test_case("error", CircularRefs::Error)]
+  #[test_case("placeholder", CircularRefs::Placeholder)]
+  #[test_case("depth=3", CircularRefs::Depth(3))]
+  #[test_case("link", CircularRefs::Link)]
+  fn circular_refs_from_str_tests(value: &str, expected: CircularRefs) -> Result<(), anyhow::Error> {
+    assert_eq!(value.parse::<CircularRefs>()?, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn referenced_documents_should_follow_all_external_references() -> Result<(), anyhow::Error> {
+    let document = DocumentPath::parse("_samples/resolver/simple1.yaml")?;
+    let documents = referenced_documents(&document, &Default::default(), &mut Default::default());
+    let expected = ["simple1.yaml", "simple2.json", "simple3.yaml", "simple4.json"]
+      .iter()
+      .map(|f| DocumentPath::parse(f)?.relate_from(&document))
+      .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(documents, expected);
+    Ok(())
+  }
+
+  #[rustfmt::skip]
+  #[test_case("", "", true, "", None, None)]
+  #[test_case("_samples/petshop.yaml", "../test.json", false, "test.json", None, None)]
+  #[test_case("_samples/petshop.yaml", "test.json", false, "_samples/test.json", None, None)]
+  #[test_case("_samples/petshop.yaml", "#test", true, "_samples/petshop.yaml", Some("test"), Some("test"))]
+  #[test_case("_samples/petshop.yaml", "test.json#test", false, "_samples/test.json", Some("test"), Some("test"))]
+  #[test_case("_samples/petshop.yaml", "http://google.com/test.json#test", false, "http://google.com/test.json", Some("test"), Some("test"))]
+  #[test_case("test.yaml", "test.yaml#/path", true, "test.yaml", Some("/path"), Some("path"))]
+  #[test_case("https://petstore.swagger.io/v2/swagger.json", "#/definitions/Pet", true, "https://petstore.swagger.io/v2/swagger.json", Some("/definitions/Pet"), Some("Pet"))]
+  #[test_case("https://petstore.swagger.io/v2/swagger.json", "http://google.com/test.json#test", false, "http://google.com/test.json", Some("test"), Some("test"))]
+  #[test_case("https://petstore.swagger.io/v2/swagger.json", "http://google.com/test.json", false, "http://google.com/test.json", None, None)]
+  #[test_case("https://petstore.swagger.io/v2/swagger.json", "../test.json", false, "https://petstore.swagger.io/test.json", None, None)]
+  #[test_case("https://petstore.swagger.io/v2/swagger.json", "../test.json#fragment", false, "https://petstore.swagger.io/test.json", Some("fragment"), Some("fragment"))]
+  fn refinfo_parse_tests(
+    current_doc: &str,
+    ref_path: &str,
+    expected_is_nested: bool,
+    expected_document_path: &str,
+    expected_path: Option<&str>,
+    expected_ref_friendly_name: Option<&str>,
+  ) {
+    let current_doc = DocumentPath::parse(current_doc).expect("?");
+    let ref_info = RefInfo::parse(&current_doc, ref_path).expect("Should work");
+    assert_eq!(ref_info.path, expected_path.map(|s| s.to_string()));
+    assert_eq!(ref_info.is_nested, expected_is_nested);
+    
+    let expected_document_path =
+    if cfg!(windows) && !expected_is_nested {
+      DocumentPath::parse(expected_document_path.replace('/',"\\").as_str()).expect("?")
+    } else {
+      DocumentPath::parse(expected_document_path).expect("?")
+    };
+    assert_eq!(ref_info.document_path, expected_document_path);
+    
+    assert_eq!(ref_info.ref_friendly_name, expected_ref_friendly_name.map(|s| s.to_string()));
+  }
+
+  #[test]
+  fn reference_with_more_than_1_sharp_should_fail() {
+    let failed = RefInfo::parse(&DocumentPath::None, "you.shall#not#path");
+    let err = failed.expect_err("Should be an error");
+    assert_eq!(
+      err.to_string(),
+      "`$ref` value `you.shall#not#path` parse error. There should be no more than 2 parts separated by # in a reference path."
+    );
+  }
+
+  #[test]
+  fn very_tricky_test() -> Result<(), anyhow::Error> {
+    let document = DocumentPath::parse("_samples/resolver/simple1.yaml")?;
+    let json = resolve_refs(document, &Default::default(), &mut Default::default(), &mut Default::default())?;
+    let string = json.to_string();
+    assert!(!string.contains(REF));
+
+    let expected = json!({
+      "test": {
+        "this": "will load multiple files"
+      },
+      "finalvalue": {
+        "value": "this is the real final value"
+      },
+      "value": {
+        "subvalue": {
+          "value": "this is the real final value",
+          "x-fromRef": "simple3.yaml#/subSubValue/value",
+          "x-refName": "value"
+        },
+        "x-fromRef": "simple2.json",
+        "x-refName": ""
+      }
+    });
+    assert_eq!(*json, expected);
+
+    Ok(())
+  }
+}
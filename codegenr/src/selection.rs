@@ -0,0 +1,110 @@
+use crate::resolver::unescape_pointer_token;
+use serde_json::Value;
+use thiserror::Error;
+
+const WILDCARD_SEGMENT: &str = "*";
+
+#[derive(Error, Debug)]
+pub enum SelectionError {
+  #[error("The `{0}` selection is not a json pointer: it should be empty or start with a `/`.")]
+  NotAPointer(String),
+}
+
+/// A node matched by a selection, with the key (or index) it has in its parent
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectedNode<'a> {
+  pub key: String,
+  pub node: &'a Value,
+}
+
+/// Selects the nodes of a document matching a json pointer, in which a `*` segment matches
+/// all the properties of an object or all the items of an array.
+/// eg: `/components/schemas/*` selects all the schemas of an openapi document.
+#[::tracing::instrument(level = "trace", skip(document))]
+pub fn select_nodes<'a>(document: &'a Value, selection: &str) -> Result<Vec<SelectedNode<'a>>, SelectionError> {
+  if !selection.is_empty() && !selection.starts_with('/') {
+    return Err(SelectionError::NotAPointer(selection.into()));
+  }
+
+  let mut selected = vec![SelectedNode {
+    key: Default::default(),
+    node: document,
+  }];
+  for segment in selection.split('/').skip(1) {
+    let segment = unescape_pointer_token(segment);
+    selected = selected.into_iter().flat_map(|parent| children(parent.node, &segment)).collect();
+  }
+  Ok(selected)
+}
+
+fn children<'a>(node: &'a Value, segment: &str) -> Vec<SelectedNode<'a>> {
+  let selected = |(key, node)| SelectedNode { key, node };
+  match (node, segment) {
+    (Value::Object(map), WILDCARD_SEGMENT) => map.iter().map(|(k, v)| (k.clone(), v)).map(selected).collect(),
+    (Value::Array(items), WILDCARD_SEGMENT) => items.iter().enumerate().map(|(i, v)| (i.to_string(), v)).map(selected).collect(),
+    (Value::Object(map), key) => map.get(key).map(|v| (key.to_string(), v)).map(selected).into_iter().collect(),
+    (Value::Array(items), index) => index
+      .parse::<usize>()
+      .ok()
+      .and_then(|i| items.get(i))
+      .map(|v| (index.to_string(), v))
+      .map(selected)
+      .into_iter()
+      .collect(),
+    _ => vec![],
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use test_case::test_case;
+
+  fn document() -> Value {
+    json!({
+      "components": {
+        "schemas": {
+          "Pet": { "type": "object" },
+          "Error": { "type": "string" },
+          "a/b": { "type": "number" }
+        }
+      },
+      "tags": [ { "name": "pets" }, { "name": "stores" } ]
+    })
+  }
+
+  #[test_case("/components/schemas/*", &["Pet", "Error", "a/b"])]
+  #[test_case("/components/schemas/Pet", &["Pet"])]
+  #[test_case("/components/schemas/a~1b", &["a/b"])]
+  #[test_case("/components/*/Error", &["Error"])]
+  #[test_case("/tags/*", &["0", "1"])]
+  #[test_case("/tags/1/name", &["name"])]
+  #[test_case("/components/missing/*", &[])]
+  #[test_case("", &[""])]
+  fn select_nodes_tests(selection: &str, expected_keys: &[&str]) -> anyhow::Result<()> {
+    let document = document();
+    let selected = select_nodes(&document, selection)?;
+    assert_eq!(selected.iter().map(|s| s.key.as_str()).collect::<Vec<_>>(), expected_keys);
+    Ok(())
+  }
+
+  #[test]
+  fn select_nodes_should_return_the_matched_nodes() -> anyhow::Result<()> {
+    let document = document();
+    let selected = select_nodes(&document, "/tags/*/name")?;
+    assert_eq!(
+      selected.iter().map(|s| s.node).collect::<Vec<_>>(),
+      vec![&json!("pets"), &json!("stores")]
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn select_nodes_should_fail_on_relative_selection() {
+    assert!(matches!(
+      select_nodes(&document(), "components"),
+      Err(SelectionError::NotAPointer(_))
+    ));
+  }
+}
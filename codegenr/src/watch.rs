@@ -0,0 +1,238 @@
+use crate::{
+  custom, errors, loaders::DocumentPath, merger, render, resolver, run_codegenr, HandlebarsHash, HandlebarsReusableConf, Options,
+  OptionsMap, OriginalDocumentsHash, ResolvedDocumentsHash,
+};
+use std::{
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+  time::{Duration, SystemTime},
+};
+use tracing::{error, info};
+
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Last modification time of every file a section depends on
+type Fingerprint = BTreeMap<PathBuf, Option<SystemTime>>;
+
+/// Runs all the sections, then keeps running and re-runs the sections for which
+/// a source document (or any `$ref`ed one), a template or a custom helper changed.
+#[::tracing::instrument(level = "trace")]
+pub fn watch_all_codegenr(options_map: OptionsMap) -> Result<(), errors::CodegenrError> {
+  let mut original_cache = Default::default();
+  let mut resolved_cache = Default::default();
+  let mut reusables = Default::default();
+
+  let mut sections = Vec::with_capacity(options_map.len());
+  for (name, options) in options_map {
+    let mut section = WatchedSection::new(name, options);
+    section.run(&mut original_cache, &mut resolved_cache, &mut reusables);
+    sections.push(section);
+  }
+
+  info!("Watching for changes...");
+  loop {
+    std::thread::sleep(WATCH_POLL_INTERVAL);
+
+    let changes = sections.iter().map(|s| s.changed_files()).collect::<Vec<_>>();
+    for (section, changed) in sections.iter().zip(changes.iter()) {
+      section.invalidate(changed, &mut original_cache, &mut resolved_cache, &mut reusables);
+    }
+    for (section, changed) in sections.iter_mut().zip(changes.iter()) {
+      if !changed.is_empty() {
+        info!("Changes detected for the `{}` section: {:?}", section.name, changed);
+        section.run(&mut original_cache, &mut resolved_cache, &mut reusables);
+      }
+    }
+  }
+}
+
+struct WatchedSection {
+  name: String,
+  options: Options,
+  documents: Vec<DocumentPath>,
+  fingerprint: Fingerprint,
+}
+
+impl WatchedSection {
+  fn new(name: String, options: Options) -> Self {
+    Self {
+      name,
+      options,
+      documents: Default::default(),
+      fingerprint: Default::default(),
+    }
+  }
+
+  fn run(
+    &mut self,
+    original_cache: &mut OriginalDocumentsHash,
+    resolved_cache: &mut ResolvedDocumentsHash,
+    reusables: &mut HandlebarsHash,
+  ) {
+    info!("Running code generation section `{}`", self.name);
+    if let Err(e) = run_codegenr(self.options.clone(), original_cache, resolved_cache, reusables) {
+      error!("Error while executing the `{}` section: `{}`.", self.name, e);
+    }
+
+    let load_options = (&self.options).into();
+    self.documents = vec![];
+    for source in merger::source_documents(&self.options.source, &self.options.sources).unwrap_or_default() {
+      for document in resolver::referenced_documents(&source, &load_options, original_cache) {
+        if !self.documents.contains(&document) {
+          self.documents.push(document);
+        }
+      }
+    }
+    self.fingerprint = self.compute_fingerprint();
+  }
+
+  /// Files that were added, removed or modified since the last run
+  fn changed_files(&self) -> Vec<PathBuf> {
+    let fingerprint = self.compute_fingerprint();
+    let mut changed = fingerprint
+      .iter()
+      .filter(|(path, modified)| self.fingerprint.get(*path) != Some(modified))
+      .map(|(path, _)| path.clone())
+      .collect::<Vec<_>>();
+    changed.extend(self.fingerprint.keys().filter(|path| !fingerprint.contains_key(*path)).cloned());
+    changed
+  }
+
+  /// Forgets the cached documents and handlebars configuration affected by the changed files
+  fn invalidate(
+    &self,
+    changed: &[PathBuf],
+    original_cache: &mut OriginalDocumentsHash,
+    resolved_cache: &mut ResolvedDocumentsHash,
+    reusables: &mut HandlebarsHash,
+  ) {
+    if changed.is_empty() {
+      return;
+    }
+
+    let mut documents_changed = false;
+    for document in self.documents.iter() {
+      if let DocumentPath::FileName(file_name) = document {
+        if changed.iter().any(|c| c == Path::new(file_name)) {
+          original_cache.remove(document);
+          documents_changed = true;
+        }
+      }
+    }
+    if documents_changed {
+      resolved_cache.retain(|(document, _), _| !self.documents.contains(document));
+    }
+
+    let documents_only = changed.iter().all(|c| {
+      self
+        .documents
+        .iter()
+        .any(|d| matches!(d, DocumentPath::FileName(f) if c == Path::new(f)))
+    });
+    if !documents_only {
+      reusables.remove(&HandlebarsReusableConf::from(&self.options));
+    }
+  }
+
+  /// Lists the known documents, but also the sources, templates and custom helpers found now,
+  /// so a new file matching a `sources` glob or added in a templates folder is a change
+  fn compute_fingerprint(&self) -> Fingerprint {
+    let mut files = vec![];
+    let sources = merger::source_documents(&self.options.source, &self.options.sources).unwrap_or_default();
+    for document in self.documents.iter().chain(sources.iter()) {
+      if let DocumentPath::FileName(file_name) = document {
+        files.push(PathBuf::from(file_name));
+      }
+    }
+    for folder in self.options.templates.iter() {
+      files.push(PathBuf::from(folder));
+      if let Ok(templates) = render::get_templates_from_directory(folder) {
+        files.extend(templates.iter().map(|t| PathBuf::from(t.file_path())));
+      }
+    }
+    if let Ok(scripts) = custom::get_script_files(&self.options.custom_helpers) {
+      files.extend(scripts);
+    }
+
+    files
+      .into_iter()
+      .map(|f| {
+        let modified = std::fs::metadata(&f).and_then(|m| m.modified()).ok();
+        (f, modified)
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::filesystem::save_file_content;
+  use tempdir::TempDir;
+
+  #[test]
+  fn changed_files_should_detect_new_templates_and_modified_documents() -> anyhow::Result<()> {
+    let tmp = TempDir::new("WATCH_tests")?;
+    let root = tmp.path().to_string_lossy().to_string();
+    save_file_content(&root, "source.yaml", "value: 42")?;
+    std::fs::create_dir(tmp.path().join("templates"))?;
+    save_file_content(&root, "templates/main.hbs", "### FILE plop.txt\n{{value}}\n### /FILE")?;
+
+    let options = Options {
+      source: format!("{root}/source.yaml"),
+      output: format!("{root}/output"),
+      templates: vec![format!("{root}/templates")],
+      ..Default::default()
+    };
+    let mut original_cache = Default::default();
+    let mut resolved_cache = Default::default();
+    let mut reusables = Default::default();
+
+    let mut section = WatchedSection::new("test".into(), options);
+    section.run(&mut original_cache, &mut resolved_cache, &mut reusables);
+    assert_eq!(std::fs::read_to_string(tmp.path().join("output/plop.txt"))?, "42\n");
+    assert!(section.changed_files().is_empty());
+
+    save_file_content(&root, "templates/_partial.hbs", "partial")?;
+    assert!(section.changed_files().contains(&tmp.path().join("templates/_partial.hbs")));
+
+    save_file_content(&root, "source.yaml", "value: 43")?;
+    let source = std::fs::File::options().write(true).open(tmp.path().join("source.yaml"))?;
+    source.set_modified(SystemTime::now() + Duration::from_secs(10))?;
+    let changed = section.changed_files();
+    section.invalidate(&changed, &mut original_cache, &mut resolved_cache, &mut reusables);
+    assert!(original_cache.is_empty());
+    assert!(resolved_cache.is_empty());
+    assert!(reusables.is_empty());
+
+    section.run(&mut original_cache, &mut resolved_cache, &mut reusables);
+    assert_eq!(std::fs::read_to_string(tmp.path().join("output/plop.txt"))?, "43\n");
+    Ok(())
+  }
+
+  #[test]
+  fn changed_files_should_detect_new_sources_and_template_folders() -> anyhow::Result<()> {
+    let tmp = TempDir::new("WATCH_tests")?;
+    let root = tmp.path().to_string_lossy().to_string();
+    std::fs::create_dir(tmp.path().join("specs"))?;
+    save_file_content(&root, "specs/a.yaml", "a: 1")?;
+
+    let options = Options {
+      sources: vec![format!("{root}/specs/*.yaml")],
+      output: format!("{root}/output"),
+      templates: vec![format!("{root}/templates")],
+      ..Default::default()
+    };
+    let mut section = WatchedSection::new("test".into(), options);
+    section.run(&mut Default::default(), &mut Default::default(), &mut Default::default());
+    assert!(section.changed_files().is_empty());
+
+    save_file_content(&root, "specs/b.yaml", "b: 2")?;
+    assert_eq!(section.changed_files(), vec![tmp.path().join("specs/b.yaml")]);
+
+    section.fingerprint = section.compute_fingerprint();
+    std::fs::create_dir(tmp.path().join("templates"))?;
+    assert_eq!(section.changed_files(), vec![tmp.path().join("templates")]);
+    Ok(())
+  }
+}